@@ -0,0 +1,203 @@
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    event::{ EventEnvelope, MoonEvent },
+    pricing::format_sol_usd,
+    trades::Position,
+    utlis::embed::{ Embed, EmbedField },
+};
+
+use super::{ dexscreener_url, format_tokens, solscan_account_url, solscan_tx_url, Launch, COLOR_DEV_SELL };
+
+/// A sell by the creator of the mint.
+#[derive(Debug, Clone)]
+pub struct DevSell {
+    pub mint: Pubkey,
+    pub launch: Launch,
+    pub signature: String,
+    pub sold: u64,
+    pub lamports_received: u64,
+    pub remaining: u64,
+    /// Share of the creator's position sold in this trade, in percent
+    pub percent_of_position: f64,
+}
+
+impl DevSell {
+    /// Returns the dev sell if `envelope` is a sell of `launch`'s mint by its
+    /// creator. `position` is the creator's aggregated position including
+    /// this sell; it is only used when the balance changes are unavailable.
+    pub fn detect(launch: &Launch, envelope: &EventEnvelope, position: Position) -> Option<Self> {
+        let MoonEvent::SellEvent(sell) = &envelope.event else {
+            return None;
+        };
+        if sell.sender != launch.creator {
+            return None;
+        }
+
+        let (sold, lamports_received, remaining) = match sell.execution {
+            Some(execution) =>
+                (execution.token_amount, execution.sol_lamports, execution.token_balance_after),
+            None => (sell.amount, sell.collateral_amount, position.holdings()),
+        };
+        let before = sold.saturating_add(remaining);
+        let percent_of_position = if before == 0 {
+            100.0
+        } else {
+            ((sold as f64) / (before as f64)) * 100.0
+        };
+
+        Some(DevSell {
+            mint: sell.mint,
+            launch: launch.clone(),
+            signature: envelope.context.signature.clone(),
+            sold,
+            lamports_received,
+            remaining,
+            percent_of_position,
+        })
+    }
+
+    pub fn embed(&self, sol_usd: Option<f64>) -> Embed {
+        let decimals = self.launch.decimals;
+        let remaining_of_supply = if self.launch.total_supply == 0 {
+            0.0
+        } else {
+            ((self.remaining as f64) / (self.launch.total_supply as f64)) * 100.0
+        };
+
+        Embed {
+            title: format!("Dev sold {} $({})", self.launch.name, self.launch.symbol),
+            url: dexscreener_url(&self.mint),
+            description: Some(
+                format!(
+                    "The creator sold **{:.1}%** of their position. [Transaction]({})",
+                    self.percent_of_position,
+                    solscan_tx_url(&self.signature)
+                )
+            ),
+            color: Some(COLOR_DEV_SELL),
+            fields: vec![
+                EmbedField {
+                    name: "Contract Address".to_string(),
+                    value: format!("`{}`", self.mint),
+                    inline: false,
+                },
+                EmbedField {
+                    name: "Dev".to_string(),
+                    value: format!("[{}]({})", self.launch.creator, solscan_account_url(&self.launch.creator)),
+                    inline: false,
+                },
+                EmbedField {
+                    name: "Sold".to_string(),
+                    value: format_tokens(self.sold, decimals),
+                    inline: true,
+                },
+                EmbedField {
+                    name: "SOL Received".to_string(),
+                    value: format_sol_usd((self.lamports_received as f64) / 1_000_000_000.0, sol_usd),
+                    inline: true,
+                },
+                EmbedField {
+                    name: "Remaining".to_string(),
+                    value: format!(
+                        "{} ({:.2}% of supply)",
+                        format_tokens(self.remaining, decimals),
+                        remaining_of_supply
+                    ),
+                    inline: true,
+                }
+            ],
+            thumbnail: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{ BuyEvent, EventContext, SellEvent, TradeExecution };
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn launch() -> Launch {
+        Launch {
+            creator: Pubkey::new_unique(),
+            name: "Moon Token".to_string(),
+            symbol: "MOON".to_string(),
+            total_supply: 1_000_000_000 * LAMPORTS,
+            decimals: 9,
+        }
+    }
+
+    fn envelope(event: MoonEvent) -> EventEnvelope {
+        EventEnvelope {
+            context: EventContext {
+                signature: "dev-sell".to_string(),
+                slot: 100,
+                block_time: None,
+                fee_payer: Pubkey::default(),
+                fee: 5000,
+                compute_units: 0,
+                success: true,
+                index: 0,
+            },
+            event,
+        }
+    }
+
+    fn sell(sender: Pubkey, amount: u64, execution: Option<TradeExecution>) -> EventEnvelope {
+        envelope(MoonEvent::SellEvent(SellEvent {
+            amount,
+            collateral_amount: LAMPORTS,
+            sender,
+            mint: Pubkey::new_unique(),
+            execution,
+            ..SellEvent::default()
+        }))
+    }
+
+    #[test]
+    fn percent_sold_from_the_executed_balances() {
+        let launch = launch();
+        let execution = TradeExecution {
+            sol_lamports: 2 * LAMPORTS,
+            token_amount: 25_000_000 * LAMPORTS,
+            token_balance_after: 75_000_000 * LAMPORTS,
+            decimals: 9,
+            price: 0.0000001,
+        };
+        // The observed position is ignored when the balances are known
+        let position = Position { tokens_bought: 10 * LAMPORTS, tokens_sold: 10 * LAMPORTS, ..Position::default() };
+        let dev_sell = DevSell::detect(&launch, &sell(launch.creator, 30_000_000 * LAMPORTS, Some(execution)), position).unwrap();
+        assert_eq!(dev_sell.sold, 25_000_000 * LAMPORTS);
+        assert_eq!(dev_sell.lamports_received, 2 * LAMPORTS);
+        assert_eq!(dev_sell.remaining, 75_000_000 * LAMPORTS);
+        assert!((dev_sell.percent_of_position - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn percent_sold_from_the_position_without_balances() {
+        let launch = launch();
+        let position = Position { tokens_bought: 100 * LAMPORTS, tokens_sold: 40 * LAMPORTS, ..Position::default() };
+        let dev_sell = DevSell::detect(&launch, &sell(launch.creator, 40 * LAMPORTS, None), position).unwrap();
+        assert_eq!(dev_sell.sold, 40 * LAMPORTS);
+        assert_eq!(dev_sell.lamports_received, LAMPORTS);
+        assert_eq!(dev_sell.remaining, 60 * LAMPORTS);
+        assert!((dev_sell.percent_of_position - 40.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn selling_an_unknown_position_counts_as_everything() {
+        let launch = launch();
+        let dev_sell = DevSell::detect(&launch, &sell(launch.creator, 0, None), Position::default()).unwrap();
+        assert_eq!(dev_sell.percent_of_position, 100.0);
+    }
+
+    #[test]
+    fn ignores_other_sellers_and_buys() {
+        let launch = launch();
+        assert!(DevSell::detect(&launch, &sell(Pubkey::new_unique(), LAMPORTS, None), Position::default()).is_none());
+        let buy = envelope(MoonEvent::BuyEvent(BuyEvent { sender: launch.creator, ..BuyEvent::default() }));
+        assert!(DevSell::detect(&launch, &buy, Position::default()).is_none());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    curve::CurveState,
+    event::MigrationEvent,
+    pricing::format_sol_usd,
+    trades::MintStats,
+    utlis::embed::{ Embed, EmbedField },
+};
+
+use super::{
+    dexscreener_url,
+    format_tokens,
+    solscan_account_url,
+    solscan_tx_url,
+    Launch,
+    COLOR_GRADUATION,
+    DEFAULT_DECIMALS,
+};
+
+/// A token whose curve completed, with what the monitor knew about it.
+#[derive(Debug, Clone)]
+pub struct Graduation {
+    pub migration: MigrationEvent,
+    pub signature: String,
+    pub launch: Option<Launch>,
+    /// Last tracked curve state, before the migration
+    pub curve: Option<CurveState>,
+    /// Trades observed while the token was on the curve
+    pub stats: Option<MintStats>,
+}
+
+impl Graduation {
+    pub fn embed(&self, sol_usd: Option<f64>) -> Embed {
+        let migration = &self.migration;
+        let token = match &self.launch {
+            Some(launch) => format!("{} $({})", launch.name, launch.symbol),
+            None => migration.mint.to_string(),
+        };
+        let decimals = self.launch
+            .as_ref()
+            .map(|launch| launch.decimals)
+            .or(self.curve.as_ref().map(|curve| curve.account.decimals))
+            .unwrap_or(DEFAULT_DECIMALS);
+
+        let mut fields = vec![
+            EmbedField {
+                name: "Contract Address".to_string(),
+                value: format!("`{}`", migration.mint),
+                inline: false,
+            },
+            EmbedField {
+                name: "Pool".to_string(),
+                value: match migration.pool {
+                    Some((dex, pool)) => format!("{}: [{}]({})", dex, pool, solscan_account_url(&pool)),
+                    None => "Not created in this transaction".to_string(),
+                },
+                inline: false,
+            }
+        ];
+        if let Some(lamports) = migration.sol_lamports {
+            fields.push(EmbedField {
+                name: "SOL Migrated".to_string(),
+                value: format_sol_usd((lamports as f64) / 1_000_000_000.0, sol_usd),
+                inline: true,
+            });
+        }
+        if let Some(tokens) = migration.token_amount {
+            fields.push(EmbedField {
+                name: "Tokens Migrated".to_string(),
+                value: format_tokens(tokens, decimals),
+                inline: true,
+            });
+        }
+        if let Some(market_cap) = self.curve.as_ref().and_then(CurveState::market_cap_sol) {
+            fields.push(EmbedField {
+                name: "Final Market Cap".to_string(),
+                value: format_sol_usd(market_cap, sol_usd),
+                inline: true,
+            });
+        }
+        if let Some(stats) = &self.stats {
+            fields.push(EmbedField {
+                name: "Curve Trading".to_string(),
+                value: format!(
+                    "{} trades by {} traders, {:.2} SOL volume",
+                    stats.trade_count(),
+                    stats.unique_traders(),
+                    stats.volume_sol()
+                ),
+                inline: false,
+            });
+        }
+
+        Embed {
+            title: format!("{} graduated", token),
+            url: dexscreener_url(&migration.mint),
+            description: Some(format!("[Transaction]({})", solscan_tx_url(&self.signature))),
+            color: Some(COLOR_GRADUATION),
+            fields,
+            thumbnail: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::{ event::Dex, trades::Position };
+
+    fn migration(pool: Option<(Dex, Pubkey)>) -> MigrationEvent {
+        MigrationEvent {
+            mint: Pubkey::new_unique(),
+            curve_account: Pubkey::new_unique(),
+            migration_authority: Pubkey::new_unique(),
+            pool,
+            sol_lamports: Some(85_000_000_000),
+            token_amount: Some(200_000_000_000_000_000),
+        }
+    }
+
+    fn field<'a>(embed: &'a Embed, name: &str) -> Option<&'a str> {
+        embed.fields.iter().find(|field| field.name == name).map(|field| field.value.as_str())
+    }
+
+    #[test]
+    fn embed_with_launch_pool_and_trading() {
+        let pool = Pubkey::new_unique();
+        let mut stats = MintStats { buys: 3, sells: 1, buy_lamports: 3_000_000_000, sell_lamports: 500_000_000, ..MintStats::default() };
+        stats.positions.insert(Pubkey::new_unique(), Position::default());
+        stats.positions.insert(Pubkey::new_unique(), Position::default());
+        let graduation = Graduation {
+            migration: migration(Some((Dex::MeteoraDammV2, pool))),
+            signature: "5igna7ure".to_string(),
+            launch: Some(Launch {
+                creator: Pubkey::new_unique(),
+                name: "Moon Token".to_string(),
+                symbol: "MOON".to_string(),
+                total_supply: 1_000_000_000_000_000_000,
+                decimals: 9,
+            }),
+            curve: None,
+            stats: Some(stats),
+        };
+
+        let embed = graduation.embed(Some(150.0));
+        assert_eq!(embed.title, "Moon Token $(MOON) graduated");
+        assert_eq!(embed.color, Some(COLOR_GRADUATION));
+        assert_eq!(embed.description.as_deref(), Some("[Transaction](https://solscan.io/tx/5igna7ure)"));
+        assert_eq!(field(&embed, "Contract Address"), Some(format!("`{}`", graduation.migration.mint).as_str()));
+        assert_eq!(field(&embed, "Pool"), Some(format!("Meteora DAMM v2: [{}](https://solscan.io/account/{})", pool, pool).as_str()));
+        assert_eq!(field(&embed, "SOL Migrated"), Some("85.00 SOL ($12,750)"));
+        assert_eq!(field(&embed, "Tokens Migrated"), Some("200.00M"));
+        assert_eq!(field(&embed, "Curve Trading"), Some("4 trades by 2 traders, 3.50 SOL volume"));
+        assert_eq!(field(&embed, "Final Market Cap"), None);
+    }
+
+    #[test]
+    fn embed_without_launch_or_pool() {
+        let graduation = Graduation {
+            migration: migration(None),
+            signature: "5igna7ure".to_string(),
+            launch: None,
+            curve: None,
+            stats: None,
+        };
+
+        let embed = graduation.embed(None);
+        assert_eq!(embed.title, format!("{} graduated", graduation.migration.mint));
+        assert_eq!(field(&embed, "Pool"), Some("Not created in this transaction"));
+        assert_eq!(field(&embed, "SOL Migrated"), Some("85.00 SOL"));
+        assert_eq!(field(&embed, "Curve Trading"), None);
+    }
+}
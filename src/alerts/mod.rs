@@ -0,0 +1,235 @@
+pub mod dev_sell;
+pub mod graduation;
+pub mod whale;
+
+use std::{ collections::{ HashMap, VecDeque }, str::FromStr, sync::Mutex };
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    event::CreateEvent,
+    storage::StoredToken,
+    utlis::embed::{ Embed, WebhookClient, WebhookMessage },
+};
+
+/// Embed colors, so alert kinds are distinguishable at a glance.
+pub const COLOR_DEV_SELL: u32 = 0xe74c3c;
+pub const COLOR_WHALE_BUY: u32 = 0x2ecc71;
+pub const COLOR_WHALE_SELL: u32 = 0xe67e22;
+pub const COLOR_GRADUATION: u32 = 0xf1c40f;
+
+/// Moonshot mints use 9 decimals unless the launch says otherwise.
+pub const DEFAULT_DECIMALS: u8 = 9;
+
+/// Mints remembered as unknown to the database, least recently asked about
+/// forgotten first.
+const UNKNOWN_MINTS: usize = 10_000;
+
+/// What alerts need to know about a launch.
+#[derive(Debug, Clone)]
+pub struct Launch {
+    pub creator: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub total_supply: u64,
+    pub decimals: u8,
+}
+
+impl From<&CreateEvent> for Launch {
+    fn from(create: &CreateEvent) -> Self {
+        Launch {
+            creator: create.sender,
+            name: create.name.clone(),
+            symbol: create.symbol.clone(),
+            total_supply: create.total_supply,
+            decimals: create.decimals,
+        }
+    }
+}
+
+impl TryFrom<&StoredToken> for Launch {
+    type Error = anyhow::Error;
+
+    fn try_from(token: &StoredToken) -> anyhow::Result<Self> {
+        Ok(Launch {
+            creator: Pubkey::from_str(&token.creator)?,
+            name: token.name.clone(),
+            symbol: token.symbol.clone(),
+            total_supply: token.total_supply,
+            decimals: token.decimals,
+        })
+    }
+}
+
+/// Creator of every mint launched or traded recently, and the mints the
+/// database had no launch for.
+#[derive(Default)]
+pub struct DevTracker {
+    /// Launch and the last slot it was looked up or registered in
+    launches: Mutex<HashMap<Pubkey, (Launch, u64)>>,
+    unknown: Mutex<UnknownMints>,
+}
+
+/// Bounded LRU set. `order` may hold stale entries for a mint that was asked
+/// about again; only the one matching `mints`' tick counts.
+#[derive(Default)]
+struct UnknownMints {
+    mints: HashMap<Pubkey, u64>,
+    order: VecDeque<(Pubkey, u64)>,
+    tick: u64,
+}
+
+impl UnknownMints {
+    /// Marks `mint` most recently used, evicting the least recently used
+    /// mints past [`UNKNOWN_MINTS`].
+    fn touch(&mut self, mint: Pubkey) {
+        self.tick += 1;
+        self.mints.insert(mint, self.tick);
+        self.order.push_back((mint, self.tick));
+        while self.mints.len() > UNKNOWN_MINTS {
+            let Some((oldest, tick)) = self.order.pop_front() else {
+                break;
+            };
+            if self.mints.get(&oldest) == Some(&tick) {
+                self.mints.remove(&oldest);
+            }
+        }
+        // Drop stale entries of mints asked about repeatedly
+        if self.order.len() > 2 * UNKNOWN_MINTS {
+            let mints = &self.mints;
+            self.order.retain(|(mint, tick)| mints.get(mint) == Some(tick));
+        }
+    }
+}
+
+impl DevTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&self, mint: Pubkey, launch: Launch, slot: u64) {
+        self.unknown.lock().unwrap().mints.remove(&mint);
+        self.launches.lock().unwrap().insert(mint, (launch, slot));
+    }
+
+    /// The launch of `mint`, marking it active at `slot`.
+    pub fn launch(&self, mint: &Pubkey, slot: u64) -> Option<Launch> {
+        let mut launches = self.launches.lock().unwrap();
+        let (launch, last_slot) = launches.get_mut(mint)?;
+        *last_slot = slot.max(*last_slot);
+        Some(launch.clone())
+    }
+
+    /// Whether the database was found to have no launch for `mint`.
+    pub fn is_unknown(&self, mint: &Pubkey) -> bool {
+        let mut unknown = self.unknown.lock().unwrap();
+        if !unknown.mints.contains_key(mint) {
+            return false;
+        }
+        unknown.touch(*mint);
+        true
+    }
+
+    /// Remembers that the database has no launch for `mint`.
+    pub fn mark_unknown(&self, mint: Pubkey) {
+        self.unknown.lock().unwrap().touch(mint);
+    }
+
+    /// Forgets launches not looked up since `min_slot`, like
+    /// [`crate::trades::TradeAggregator::prune`].
+    pub fn prune(&self, min_slot: u64) {
+        self.launches.lock().unwrap().retain(|_, (_, last_slot)| *last_slot >= min_slot);
+    }
+}
+
+pub fn dexscreener_url(mint: &impl ToString) -> String {
+    format!("https://dexscreener.com/solana/{}", mint.to_string())
+}
+
+pub fn solscan_tx_url(signature: &str) -> String {
+    format!("https://solscan.io/tx/{}", signature)
+}
+
+pub fn solscan_account_url(account: &impl ToString) -> String {
+    format!("https://solscan.io/account/{}", account.to_string())
+}
+
+/// Formats a raw token amount in whole tokens with a K/M/B suffix.
+pub fn format_tokens(raw: u64, decimals: u8) -> String {
+    let tokens = (raw as f64) / (10f64).powi(decimals as i32);
+    if tokens >= 1e9 {
+        format!("{:.2}B", tokens / 1e9)
+    } else if tokens >= 1e6 {
+        format!("{:.2}M", tokens / 1e6)
+    } else if tokens >= 1e3 {
+        format!("{:.2}K", tokens / 1e3)
+    } else {
+        format!("{:.2}", tokens)
+    }
+}
+
+/// Posts a single alert embed to `webhook_url`.
+pub async fn send_alert(webhook: &WebhookClient, webhook_url: &str, embed: Embed) {
+    let title = embed.title.clone();
+    let message = WebhookMessage {
+        content: "".to_string(),
+        embeds: vec![embed],
+    };
+    if let Err(e) = webhook.send(webhook_url, &message).await {
+        eprintln!("Dropped alert \"{}\": {}", title, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn launch() -> Launch {
+        Launch {
+            creator: Pubkey::new_unique(),
+            name: "Token".to_string(),
+            symbol: "TKN".to_string(),
+            total_supply: 1_000_000_000,
+            decimals: DEFAULT_DECIMALS,
+        }
+    }
+
+    #[test]
+    fn prunes_launches_idle_since_min_slot() {
+        let devs = DevTracker::new();
+        let (idle, active) = (Pubkey::new_unique(), Pubkey::new_unique());
+        devs.register(idle, launch(), 10);
+        devs.register(active, launch(), 10);
+        assert!(devs.launch(&active, 50).is_some());
+
+        devs.prune(20);
+        assert!(devs.launch(&idle, 60).is_none());
+        assert!(devs.launch(&active, 60).is_some());
+    }
+
+    #[test]
+    fn unknown_mints_are_bounded_lru() {
+        let devs = DevTracker::new();
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        devs.mark_unknown(first);
+        devs.mark_unknown(second);
+        for _ in 0..UNKNOWN_MINTS - 1 {
+            // Keeps `first` recently used while others push `second` out
+            assert!(devs.is_unknown(&first));
+            devs.mark_unknown(Pubkey::new_unique());
+        }
+        assert!(devs.is_unknown(&first));
+        assert!(!devs.is_unknown(&second));
+        assert!(devs.unknown.lock().unwrap().order.len() <= 2 * UNKNOWN_MINTS + 1);
+    }
+
+    #[test]
+    fn registering_clears_unknown() {
+        let devs = DevTracker::new();
+        let mint = Pubkey::new_unique();
+        devs.mark_unknown(mint);
+        devs.register(mint, launch(), 1);
+        assert!(!devs.is_unknown(&mint));
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    config::WhaleAlert,
+    event::EventEnvelope,
+    pricing::format_sol_usd,
+    trades::{ Position, Trade },
+    utlis::embed::{ Embed, EmbedField },
+};
+
+use super::{
+    dexscreener_url,
+    format_tokens,
+    solscan_account_url,
+    solscan_tx_url,
+    COLOR_WHALE_BUY,
+    COLOR_WHALE_SELL,
+    Launch,
+    DEFAULT_DECIMALS,
+};
+
+/// A single buy or sell at or above the configured SOL size.
+#[derive(Debug, Clone)]
+pub struct WhaleTrade {
+    pub trade: Trade,
+    pub signature: String,
+    /// Known if the launch was seen by the monitor
+    pub launch: Option<Launch>,
+    pub decimals: u8,
+    /// Trader's token balance after the trade, from the transaction when
+    /// available, otherwise from observed trades
+    pub holdings: u64,
+    /// Trader's running totals in the mint, including this trade
+    pub position: Position,
+}
+
+impl WhaleTrade {
+    /// Returns the trade in `envelope` if its size (executed SOL when known,
+    /// otherwise the requested collateral) reaches the mint's threshold in
+    /// `config`. `launch` is only looked up for trades that do.
+    pub fn detect(
+        envelope: &EventEnvelope,
+        config: &WhaleAlert,
+        launch: impl FnOnce(&Trade) -> Option<Launch>,
+        position: Position
+    ) -> Option<Self> {
+        let trade = Trade::from_envelope(envelope)?;
+        if trade.sol() < config.threshold(&trade.mint) {
+            return None;
+        }
+        let launch = launch(&trade);
+        let execution = envelope.event.execution();
+        Some(WhaleTrade {
+            trade,
+            signature: envelope.context.signature.clone(),
+            decimals: execution
+                .map(|execution| execution.decimals)
+                .or(launch.as_ref().map(|launch| launch.decimals))
+                .unwrap_or(DEFAULT_DECIMALS),
+            holdings: execution.map_or(position.holdings(), |execution| execution.token_balance_after),
+            launch,
+            position,
+        })
+    }
+
+    pub fn embed(&self, sol_usd: Option<f64>) -> Embed {
+        let side = if self.trade.is_buy { "buy" } else { "sell" };
+        let token = match &self.launch {
+            Some(launch) => format!("{} $({})", launch.name, launch.symbol),
+            None => self.trade.mint.to_string(),
+        };
+
+        Embed {
+            title: format!("Whale {}: {:.2} SOL of {}", side, self.trade.sol(), token),
+            url: dexscreener_url(&self.trade.mint),
+            description: Some(format!("[Transaction]({})", solscan_tx_url(&self.signature))),
+            color: Some(if self.trade.is_buy { COLOR_WHALE_BUY } else { COLOR_WHALE_SELL }),
+            fields: vec![
+                EmbedField {
+                    name: "Contract Address".to_string(),
+                    value: format!("`{}`", self.trade.mint),
+                    inline: false,
+                },
+                EmbedField {
+                    name: "Trader".to_string(),
+                    value: format!("[{}]({})", self.trade.trader, solscan_account_url(&self.trade.trader)),
+                    inline: false,
+                },
+                EmbedField {
+                    name: "Size".to_string(),
+                    value: format!(
+                        "{} for {} tokens",
+                        format_sol_usd(self.trade.sol(), sol_usd),
+                        format_tokens(self.trade.token_amount, self.decimals)
+                    ),
+                    inline: false,
+                },
+                EmbedField {
+                    name: "Holdings".to_string(),
+                    value: format_tokens(self.holdings, self.decimals),
+                    inline: true,
+                },
+                EmbedField {
+                    name: "Trades".to_string(),
+                    value: self.position.trades.to_string(),
+                    inline: true,
+                },
+                EmbedField {
+                    name: "Realized".to_string(),
+                    value: format!("{:+.3} SOL", self.position.realized_sol()),
+                    inline: true,
+                }
+            ],
+            thumbnail: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ cell::Cell, collections::HashMap };
+
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::event::{ BuyEvent, EventContext, MoonEvent, SellEvent, TradeExecution };
+
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    fn envelope(event: MoonEvent) -> EventEnvelope {
+        EventEnvelope {
+            context: EventContext {
+                signature: "whale".to_string(),
+                slot: 100,
+                block_time: None,
+                fee_payer: Pubkey::default(),
+                fee: 5000,
+                compute_units: 0,
+                success: true,
+                index: 0,
+            },
+            event,
+        }
+    }
+
+    fn buy(mint: Pubkey, collateral_amount: u64, execution: Option<TradeExecution>) -> EventEnvelope {
+        envelope(MoonEvent::BuyEvent(BuyEvent {
+            amount: 1_000_000 * LAMPORTS,
+            collateral_amount,
+            slippage_bps: 100,
+            sender: Pubkey::new_unique(),
+            curve_account: Pubkey::new_unique(),
+            mint,
+            execution,
+        }))
+    }
+
+    fn config(min_sol: f64, per_mint: &[(Pubkey, f64)]) -> WhaleAlert {
+        WhaleAlert {
+            enabled: true,
+            min_sol,
+            per_mint: per_mint.iter().map(|(mint, threshold)| (mint.to_string(), *threshold)).collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn uses_the_default_threshold() {
+        let config = config(10.0, &[]);
+        let mint = Pubkey::new_unique();
+        assert!(WhaleTrade::detect(&buy(mint, 10 * LAMPORTS, None), &config, |_| None, Position::default()).is_some());
+        assert!(WhaleTrade::detect(&buy(mint, 10 * LAMPORTS - 1, None), &config, |_| None, Position::default()).is_none());
+    }
+
+    #[test]
+    fn per_mint_threshold_overrides_the_default() {
+        let watched = Pubkey::new_unique();
+        let config = config(10.0, &[(watched, 2.0)]);
+        assert!(WhaleTrade::detect(&buy(watched, 3 * LAMPORTS, None), &config, |_| None, Position::default()).is_some());
+        let other = Pubkey::new_unique();
+        assert!(WhaleTrade::detect(&buy(other, 3 * LAMPORTS, None), &config, |_| None, Position::default()).is_none());
+    }
+
+    /// The executed amount decides, not the collateral the trader offered.
+    #[test]
+    fn executed_size_is_compared() {
+        let config = config(10.0, &[]);
+        let execution = TradeExecution {
+            sol_lamports: 4 * LAMPORTS,
+            token_amount: 400_000 * LAMPORTS,
+            token_balance_after: 900_000 * LAMPORTS,
+            decimals: 6,
+            price: 0.00001,
+        };
+        let mint = Pubkey::new_unique();
+        assert!(WhaleTrade::detect(&buy(mint, 20 * LAMPORTS, Some(execution)), &config, |_| None, Position::default()).is_none());
+
+        let execution = TradeExecution { sol_lamports: 12 * LAMPORTS, ..execution };
+        let whale = WhaleTrade::detect(&buy(mint, 20 * LAMPORTS, Some(execution)), &config, |_| None, Position::default()).unwrap();
+        assert_eq!(whale.trade.lamports, 12 * LAMPORTS);
+        assert_eq!(whale.holdings, 900_000 * LAMPORTS);
+        assert_eq!(whale.decimals, 6);
+    }
+
+    #[test]
+    fn holdings_fall_back_to_the_position() {
+        let config = config(1.0, &[]);
+        let mint = Pubkey::new_unique();
+        let sell = envelope(MoonEvent::SellEvent(SellEvent {
+            amount: 300 * LAMPORTS,
+            collateral_amount: 2 * LAMPORTS,
+            sender: Pubkey::new_unique(),
+            mint,
+            ..SellEvent::default()
+        }));
+        let position = Position { tokens_bought: 1_000 * LAMPORTS, tokens_sold: 300 * LAMPORTS, ..Position::default() };
+        let whale = WhaleTrade::detect(&sell, &config, |_| None, position).unwrap();
+        assert!(!whale.trade.is_buy);
+        assert_eq!(whale.holdings, 700 * LAMPORTS);
+        assert_eq!(whale.decimals, DEFAULT_DECIMALS);
+    }
+
+    #[test]
+    fn launch_is_only_looked_up_for_whales() {
+        let config = config(10.0, &[]);
+        let mint = Pubkey::new_unique();
+        let lookups = Cell::new(0);
+        let lookup = |_: &Trade| {
+            lookups.set(lookups.get() + 1);
+            None
+        };
+        WhaleTrade::detect(&buy(mint, LAMPORTS, None), &config, lookup, Position::default());
+        assert_eq!(lookups.get(), 0);
+        WhaleTrade::detect(&buy(mint, 50 * LAMPORTS, None), &config, lookup, Position::default());
+        assert_eq!(lookups.get(), 1);
+    }
+}
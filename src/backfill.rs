@@ -0,0 +1,266 @@
+use std::{ collections::{ HashSet, VecDeque }, str::FromStr, sync::Mutex };
+
+use futures_util::{ stream, StreamExt };
+use serde_json::{ json, Value };
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_request::RpcRequest,
+    rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{ commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature };
+use tokio::sync::mpsc;
+
+use crate::event::TransactionNotification;
+
+/// Signatures remembered for de-duplication between live and backfilled transactions.
+const SEEN_CAPACITY: usize = 10_000;
+/// `getSignaturesForAddress` page size (the RPC maximum).
+const PAGE_LIMIT: usize = 1000;
+/// Give up walking back after this many pages so a very old slot can't stall the monitor.
+const MAX_PAGES: usize = 20;
+/// Missed transactions fetched in parallel.
+const FETCH_CONCURRENCY: usize = 8;
+
+/// Remembers the last processed slot and recently seen signatures so that
+/// transactions missed while the websocket was down can be replayed.
+pub struct Backfill {
+    program_id: Pubkey,
+    last_slot: Option<u64>,
+    seen: HashSet<String>,
+    seen_order: VecDeque<String>,
+}
+
+impl Backfill {
+    pub fn new(program_id: Pubkey) -> Self {
+        Self {
+            program_id,
+            last_slot: None,
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+        }
+    }
+
+    pub fn last_slot(&self) -> Option<u64> {
+        self.last_slot
+    }
+
+    /// Records a notification about to be processed. Returns `false` if its
+    /// signature was already handled and it should be skipped.
+    pub fn observe(&mut self, notification: &TransactionNotification) -> bool {
+        let slot = notification.slot();
+        self.last_slot = Some(self.last_slot.map_or(slot, |last| last.max(slot)));
+
+        match notification.signature() {
+            Some(signature) => self.remember(signature),
+            None => true,
+        }
+    }
+
+    fn remember(&mut self, signature: &str) -> bool {
+        if !self.seen.insert(signature.to_string()) {
+            return false;
+        }
+        self.seen_order.push_back(signature.to_string());
+        if self.seen_order.len() > SEEN_CAPACITY {
+            if let Some(oldest) = self.seen_order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Walks the program's signatures back to the last processed slot, fetches
+/// the missed, not yet seen, successful transactions (a few at a time) and
+/// sends them to `ingest` oldest first, wrapped as `transactionNotification`
+/// messages so they go through the same path as live ones. Meant to run in
+/// its own task; `backfill` is only locked between awaits. Returns how many
+/// transactions were queued.
+pub async fn recover(
+    backfill: &Mutex<Backfill>,
+    client: &RpcClient,
+    commitment: CommitmentConfig,
+    ingest: &mpsc::Sender<Value>
+) -> anyhow::Result<usize> {
+    let (program_id, last_slot) = {
+        let backfill = backfill.lock().unwrap();
+        (backfill.program_id, backfill.last_slot)
+    };
+    let Some(last_slot) = last_slot else {
+        return Ok(0);
+    };
+
+    let mut missed: Vec<RpcConfirmedTransactionStatusWithSignature> = Vec::new();
+    let mut before: Option<Signature> = None;
+    for page_number in 1..=MAX_PAGES {
+        let page = client.get_signatures_for_address_with_config(
+            &program_id,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until: None,
+                limit: Some(PAGE_LIMIT),
+                commitment: Some(history_commitment(commitment)),
+            }
+        ).await?;
+
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&last.signature)?);
+        let oldest_slot = last.slot;
+        let reached = oldest_slot < last_slot;
+        let page_len = page.len();
+
+        missed.extend(page.into_iter().filter(|status| status.slot >= last_slot));
+        if reached || page_len < PAGE_LIMIT {
+            break;
+        }
+        if page_number == MAX_PAGES {
+            eprintln!(
+                "Backfill gave up after {} pages at slot {}; transactions from slot {} up to there are not recovered",
+                MAX_PAGES,
+                oldest_slot,
+                last_slot
+            );
+        }
+    }
+
+    let missed: Vec<_> = {
+        let backfill = backfill.lock().unwrap();
+        missed
+            .into_iter()
+            .rev()
+            .filter(|status| status.err.is_none() && !backfill.seen.contains(&status.signature))
+            .collect()
+    };
+    println!("Backfilling {} missed transactions", missed.len());
+
+    let mut fetched = stream
+        ::iter(missed)
+        .map(|status| async move {
+            let result = fetch_notification(client, &status.signature, status.slot).await;
+            (status.signature, result)
+        })
+        .buffered(FETCH_CONCURRENCY);
+    let mut queued = 0;
+    while let Some((signature, result)) = fetched.next().await {
+        match result {
+            Ok(Some(notification)) => {
+                if ingest.send(notification).await.is_err() {
+                    anyhow::bail!("ingest queue is closed");
+                }
+                queued += 1;
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to fetch transaction {}: {}", signature, e),
+        }
+    }
+    Ok(queued)
+}
+
+/// `commitment`, raised to `confirmed` if lower: `getSignaturesForAddress`
+/// doesn't accept `processed`.
+pub fn history_commitment(commitment: CommitmentConfig) -> CommitmentConfig {
+    if commitment.is_at_least_confirmed() { commitment } else { CommitmentConfig::confirmed() }
+}
+
+/// Fetches a transaction in `jsonParsed` encoding and wraps it as a
+/// `transactionNotification` message, so it can go through
+/// `parse_pump_event` like a live one. `None` if the node doesn't have it.
+pub async fn fetch_notification(
+    client: &RpcClient,
+    signature: &str,
+    slot: u64
+) -> anyhow::Result<Option<Value>> {
+    let transaction: Value = client.send(
+        RpcRequest::GetTransaction,
+        json!([
+            signature,
+            {
+                "encoding": "jsonParsed",
+                "commitment": "confirmed",
+                "maxSupportedTransactionVersion": 0
+            }
+        ])
+    ).await?;
+    if transaction.is_null() {
+        return Ok(None);
+    }
+
+    Ok(
+        Some(
+            json!({
+                "jsonrpc": "2.0",
+                "method": "transactionNotification",
+                "params": {
+                    "subscription": 0,
+                    "result": {
+                        "context": { "slot": slot },
+                        "value": {
+                            "slot": slot,
+                            "signature": signature,
+                            "transaction": transaction
+                        }
+                    }
+                }
+            })
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(signature: &str, slot: u64) -> TransactionNotification {
+        serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "method": "transactionNotification",
+            "params": {
+                "subscription": 1,
+                "result": {
+                    "context": { "slot": slot },
+                    "value": { "slot": slot, "signature": signature, "transaction": null }
+                }
+            }
+        })).unwrap()
+    }
+
+    #[test]
+    fn observe_skips_seen_signatures() {
+        let mut backfill = Backfill::new(Pubkey::new_unique());
+        assert!(backfill.observe(&notification("first", 100)));
+        assert!(backfill.observe(&notification("second", 101)));
+        assert!(!backfill.observe(&notification("first", 100)));
+    }
+
+    #[test]
+    fn last_slot_never_moves_back() {
+        let mut backfill = Backfill::new(Pubkey::new_unique());
+        assert_eq!(backfill.last_slot(), None);
+        backfill.observe(&notification("first", 200));
+        backfill.observe(&notification("backfilled", 150));
+        assert_eq!(backfill.last_slot(), Some(200));
+        // A repeated signature still counts as processed up to its slot
+        backfill.observe(&notification("first", 200));
+        backfill.observe(&notification("third", 250));
+        assert_eq!(backfill.last_slot(), Some(250));
+    }
+
+    #[test]
+    fn forgets_the_oldest_signatures_beyond_capacity() {
+        let mut backfill = Backfill::new(Pubkey::new_unique());
+        for index in 0..=SEEN_CAPACITY {
+            assert!(backfill.observe(&notification(&format!("signature-{}", index), 1)));
+        }
+        assert!(backfill.observe(&notification("signature-0", 1)));
+        assert!(!backfill.observe(&notification(&format!("signature-{}", SEEN_CAPACITY), 1)));
+    }
+
+    #[test]
+    fn history_commitment_is_at_least_confirmed() {
+        assert_eq!(history_commitment(CommitmentConfig::processed()), CommitmentConfig::confirmed());
+        assert_eq!(history_commitment(CommitmentConfig::finalized()), CommitmentConfig::finalized());
+    }
+}
@@ -0,0 +1,299 @@
+use std::{ collections::VecDeque, fmt, str::FromStr, sync::Mutex };
+
+use solana_sdk::pubkey::Pubkey;
+use unicode_normalization::{ char::is_combining_mark, UnicodeNormalization };
+
+use crate::{ config::CloneConfig, event::CreateEvent, storage::Storage };
+
+/// Normalized names this short only match exactly; fuzzy matching them
+/// would flag half of all tickers.
+const MIN_FUZZY_LENGTH: usize = 4;
+
+/// Which part of a launch looks copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloneField {
+    Name,
+    Symbol,
+}
+
+impl fmt::Display for CloneField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CloneField::Name => "name",
+            CloneField::Symbol => "symbol",
+        })
+    }
+}
+
+/// What a launch appears to copy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloneTarget {
+    /// An entry of the configured watchlist
+    Watchlist(String),
+    /// An earlier launch
+    Launch {
+        mint: Pubkey,
+        name: String,
+        symbol: String,
+    },
+}
+
+impl fmt::Display for CloneTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CloneTarget::Watchlist(entry) => write!(f, "{} (watchlist)", entry),
+            CloneTarget::Launch { mint, name, symbol } => write!(f, "{} $({}) {}", name, symbol, mint),
+        }
+    }
+}
+
+/// A launch whose name or symbol matches a watchlist entry or an earlier
+/// launch after normalization.
+#[derive(Debug, Clone)]
+pub struct CloneMatch {
+    pub target: CloneTarget,
+    pub field: CloneField,
+    /// Similarity of the normalized strings, 1.0 when they are equal
+    pub similarity: f64,
+}
+
+impl fmt::Display for CloneMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "possible clone of {} ({} ", self.target, self.field)?;
+        if self.similarity >= 1.0 {
+            write!(f, "matches)")
+        } else {
+            write!(f, "{:.0}% similar)", self.similarity * 100.0)
+        }
+    }
+}
+
+struct RecentLaunch {
+    mint: Pubkey,
+    name: String,
+    symbol: String,
+    normalized_name: String,
+    normalized_symbol: String,
+}
+
+/// Compares new launches against the watchlist and the most recent
+/// launches. Names and symbols are normalized first (case, accents,
+/// look-alike characters, whitespace and punctuation), then compared by
+/// edit distance.
+pub struct CloneDetector {
+    config: CloneConfig,
+    watchlist: Vec<(String, String)>,
+    recent: Mutex<VecDeque<RecentLaunch>>,
+}
+
+impl CloneDetector {
+    /// Seeds the recent launches from the database, if there is one.
+    pub fn load(config: CloneConfig, storage: Option<&Storage>) -> anyhow::Result<Self> {
+        let watchlist = config.watchlist
+            .iter()
+            .map(|entry| (entry.clone(), normalize(entry)))
+            .filter(|(_, normalized)| !normalized.is_empty())
+            .collect();
+        let mut recent = VecDeque::with_capacity(config.recent_launches);
+        if let Some(storage) = storage {
+            // Newest first; keep the order launches arrive in
+            for token in storage.recent_tokens(config.recent_launches)?.into_iter().rev() {
+                let Ok(mint) = Pubkey::from_str(&token.mint) else {
+                    continue;
+                };
+                recent.push_back(RecentLaunch {
+                    mint,
+                    normalized_name: normalize(&token.name),
+                    normalized_symbol: normalize(&token.symbol),
+                    name: token.name,
+                    symbol: token.symbol,
+                });
+            }
+        }
+        Ok(Self { config, watchlist, recent: Mutex::new(recent) })
+    }
+
+    /// Best match for `create`, preferring watchlist entries over launches
+    /// and the most similar launch among those, then remembers `create` for
+    /// later launches.
+    pub fn check(&self, create: &CreateEvent) -> Option<CloneMatch> {
+        let name = normalize(&create.name);
+        let symbol = normalize(&create.symbol);
+        let fields = [(CloneField::Name, name.as_str()), (CloneField::Symbol, symbol.as_str())];
+
+        let watchlisted = self.watchlist.iter().find_map(|(entry, normalized)| {
+            fields.iter().find_map(|(field, value)| {
+                let similarity = self.similar(value, normalized)?;
+                Some(CloneMatch { target: CloneTarget::Watchlist(entry.clone()), field: *field, similarity })
+            })
+        });
+
+        let mut recent = self.recent.lock().unwrap();
+        let found = watchlisted.or_else(|| {
+            recent
+                .iter()
+                .filter(|launch| launch.mint != create.mint)
+                .flat_map(|launch| {
+                    [
+                        (CloneField::Name, name.as_str(), &launch.normalized_name),
+                        (CloneField::Symbol, symbol.as_str(), &launch.normalized_symbol),
+                    ].map(|(field, value, other)| {
+                        let similarity = self.similar(value, other)?;
+                        Some(CloneMatch {
+                            target: CloneTarget::Launch {
+                                mint: launch.mint,
+                                name: launch.name.clone(),
+                                symbol: launch.symbol.clone(),
+                            },
+                            field,
+                            similarity,
+                        })
+                    })
+                })
+                .flatten()
+                .max_by(|a, b| a.similarity.total_cmp(&b.similarity))
+        });
+
+        if !recent.iter().any(|launch| launch.mint == create.mint) {
+            recent.push_back(RecentLaunch {
+                mint: create.mint,
+                name: create.name.clone(),
+                symbol: create.symbol.clone(),
+                normalized_name: name,
+                normalized_symbol: symbol,
+            });
+            while recent.len() > self.config.recent_launches {
+                recent.pop_front();
+            }
+        }
+        found
+    }
+
+    /// Similarity of two normalized strings if it reaches `min_similarity`.
+    fn similar(&self, a: &str, b: &str) -> Option<f64> {
+        if a.is_empty() || b.is_empty() {
+            return None;
+        }
+        if a == b {
+            return Some(1.0);
+        }
+        if a.chars().count().min(b.chars().count()) < MIN_FUZZY_LENGTH {
+            return None;
+        }
+        Some(similarity(a, b)).filter(|similarity| *similarity >= self.config.min_similarity)
+    }
+}
+
+/// Folds `text` to a form in which look-alike names compare equal: Unicode
+/// compatibility forms (fullwidth, math letters) and accents are folded,
+/// Cyrillic and Greek homoglyphs and digits used as letters become Latin
+/// letters, everything but letters and digits is dropped, and letter pairs
+/// that read as one letter (`rn`, `vv`) are merged.
+pub fn normalize(text: &str) -> String {
+    let folded: String = text
+        .trim()
+        .trim_start_matches('$')
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .map(homoglyph)
+        .filter(|c| c.is_alphanumeric())
+        .collect();
+    folded.replace("rn", "m").replace("vv", "w")
+}
+
+/// Maps a lowercase character to the Latin letter it passes for. `i`, `l`,
+/// `1` and `|` are indistinguishable in many fonts, so they share `l`.
+fn homoglyph(c: char) -> char {
+    match c {
+        'а' | 'α' | '4' => 'a',
+        'в' | 'β' | '8' => 'b',
+        'с' | 'ϲ' => 'c',
+        'ԁ' => 'd',
+        'е' | 'ε' | 'є' | '3' => 'e',
+        'һ' | 'н' => 'h',
+        'i' | 'і' | 'ι' | 'ї' | 'ı' | 'ӏ' | '1' | '!' | '|' => 'l',
+        'ј' => 'j',
+        'к' | 'κ' => 'k',
+        'м' => 'm',
+        'η' | 'п' => 'n',
+        'о' | 'ο' | 'σ' | '0' => 'o',
+        'р' | 'ρ' => 'p',
+        'ѕ' | '5' | '$' => 's',
+        'т' | 'τ' | '7' => 't',
+        'υ' | 'μ' => 'u',
+        'ν' => 'v',
+        'ш' | 'ω' => 'w',
+        'х' | 'χ' => 'x',
+        'у' | 'γ' => 'y',
+        other => other,
+    }
+}
+
+/// 1 minus the Levenshtein distance of `a` and `b` over the longer length.
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    1.0 - (previous[b.len()] as f64) / (a.len().max(b.len()) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_folds_case_accents_and_symbols() {
+        assert_eq!(normalize("  $Pepe "), "pepe");
+        assert_eq!(normalize("Pépé Coin!"), normalize("pepe coinl"));
+        assert_eq!(normalize("ＰＥＰＥ"), "pepe");
+        assert_eq!(normalize("dog-wif.hat"), normalize("DOGWIFHAT"));
+    }
+
+    #[test]
+    fn normalize_folds_homoglyphs() {
+        // Cyrillic а, е, о, р
+        assert_eq!(normalize("Реро"), normalize("pepo"));
+        assert_eq!(normalize("B0NK"), normalize("bonk"));
+        assert_eq!(normalize("5HIB"), normalize("shib"));
+    }
+
+    #[test]
+    fn normalize_treats_i_l_1_and_bar_alike() {
+        let wif = normalize("WIF");
+        for lookalike in ["WlF", "W1F", "W|F", "wif", "WіF"] {
+            assert_eq!(normalize(lookalike), wif, "{}", lookalike);
+        }
+    }
+
+    #[test]
+    fn normalize_merges_letter_pairs() {
+        assert_eq!(normalize("rnoon"), normalize("moon"));
+        assert_eq!(normalize("VVIF"), normalize("wif"));
+        assert_eq!(normalize("burn"), normalize("bum"));
+    }
+
+    #[test]
+    fn similarity_is_one_minus_relative_edit_distance() {
+        assert_eq!(similarity("bonk", "bonk"), 1.0);
+        assert_eq!(similarity("bonk", "bork"), 0.75);
+        assert_eq!(similarity("bonk", "bonks"), 0.8);
+        assert!((similarity("kitten", "sitting") - (1.0 - 3.0 / 7.0)).abs() < 1e-9);
+        assert_eq!(similarity("abcd", "wxyz"), 0.0);
+    }
+
+    #[test]
+    fn similarity_is_symmetric() {
+        assert_eq!(similarity("popcat", "popkat"), similarity("popkat", "popcat"));
+        assert_eq!(similarity("mew", "meow"), similarity("meow", "mew"));
+    }
+}
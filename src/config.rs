@@ -0,0 +1,679 @@
+use std::{ collections::HashMap, fmt, path::{ Path, PathBuf }, str::FromStr, time::Duration };
+
+use anyhow::Context;
+use clap::{ Parser, ValueEnum };
+use serde::Deserialize;
+use solana_sdk::{ commitment_config::CommitmentConfig, pubkey::Pubkey };
+use url::Url;
+
+use crate::{
+    event::MOONSHOT_PROGRAM_ID,
+    pipeline::Stage,
+    pricing::PriceSource,
+    reputation::{ CreatorFlag, Reputation },
+};
+
+/// Config file looked up in the working directory when `--config` isn't given.
+const DEFAULT_CONFIG_PATH: &str = "moonshot-monitor.toml";
+const DEFAULT_DATABASE_PATH: &str = "moonshot-monitor.db";
+
+/// Watches the Moonshot program and posts launches to Discord.
+///
+/// Settings come from the TOML config file; every flag below (or its
+/// environment variable) overrides the matching file entry.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Path to the TOML config file [default: ./moonshot-monitor.toml if present]
+    #[arg(short, long, env = "MOONSHOT_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// HTTP(S) JSON-RPC endpoint
+    #[arg(long, env = "MOONSHOT_RPC_URL")]
+    pub rpc_url: Option<String>,
+    /// WebSocket endpoint supporting `transactionSubscribe`
+    #[arg(long, env = "MOONSHOT_WS_URL")]
+    pub ws_url: Option<String>,
+    /// Program to monitor
+    #[arg(long, env = "MOONSHOT_PROGRAM_ID")]
+    pub program_id: Option<String>,
+    /// Commitment level of the transaction subscription
+    #[arg(long, env = "MOONSHOT_COMMITMENT")]
+    pub commitment: Option<Commitment>,
+    /// Discord webhook for launch embeds
+    #[arg(long, env = "MOONSHOT_LAUNCH_WEBHOOK")]
+    pub launch_webhook: Option<String>,
+    /// Discord webhook for alerts [default: the launch webhook]
+    #[arg(long, env = "MOONSHOT_ALERT_WEBHOOK")]
+    pub alert_webhook: Option<String>,
+    /// SQLite database the event history is written to
+    #[arg(long, env = "MOONSHOT_DATABASE")]
+    pub database: Option<PathBuf>,
+    #[command(flatten)]
+    pub features: FeatureFlags,
+}
+
+/// Overrides of the `[features]` switches, e.g. `--backfill false` or
+/// `MOONSHOT_LOGO_MATCHING=false`.
+#[derive(Debug, Default, clap::Args)]
+pub struct FeatureFlags {
+    /// Replay transactions missed while the websocket was down
+    #[arg(long, env = "MOONSHOT_BACKFILL", value_name = "BOOL")]
+    pub backfill: Option<bool>,
+    /// Fetch and track bonding curve state for traded mints
+    #[arg(long, env = "MOONSHOT_CURVE_TRACKING", value_name = "BOOL")]
+    pub curve_tracking: Option<bool>,
+    /// Show USD values next to SOL amounts
+    #[arg(long, env = "MOONSHOT_USD_PRICING", value_name = "BOOL")]
+    pub usd_pricing: Option<bool>,
+    /// Look up the creator's earlier launches for the create embed
+    #[arg(long, env = "MOONSHOT_CREATOR_HISTORY", value_name = "BOOL")]
+    pub creator_history: Option<bool>,
+    /// Flag launches reusing an earlier launch's image
+    #[arg(long, env = "MOONSHOT_LOGO_MATCHING", value_name = "BOOL")]
+    pub logo_matching: Option<bool>,
+    /// Flag launches copying a watchlisted or recent name or symbol
+    #[arg(long, env = "MOONSHOT_CLONE_DETECTION", value_name = "BOOL")]
+    pub clone_detection: Option<bool>,
+    /// Persist events to the SQLite database
+    #[arg(long, env = "MOONSHOT_STORAGE", value_name = "BOOL")]
+    pub storage: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Commitment {
+    #[default]
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl fmt::Display for Commitment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Processed => write!(f, "processed"),
+            Self::Confirmed => write!(f, "confirmed"),
+            Self::Finalized => write!(f, "finalized"),
+        }
+    }
+}
+
+impl From<Commitment> for CommitmentConfig {
+    fn from(commitment: Commitment) -> Self {
+        match commitment {
+            Commitment::Processed => Self::processed(),
+            Commitment::Confirmed => Self::confirmed(),
+            Commitment::Finalized => Self::finalized(),
+        }
+    }
+}
+
+/// Config file layout; every field is optional so that flags and
+/// environment variables can fill in the rest.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    rpc_url: Option<String>,
+    ws_url: Option<String>,
+    program_id: Option<String>,
+    commitment: Option<Commitment>,
+    database: Option<PathBuf>,
+    #[serde(default)]
+    webhooks: FileWebhooks,
+    #[serde(default)]
+    features: Features,
+    #[serde(default)]
+    pricing: FilePricing,
+    #[serde(default)]
+    alerts: Alerts,
+    #[serde(default)]
+    pipeline: PipelineConfig,
+    #[serde(default)]
+    reputation: ReputationConfig,
+    #[serde(default)]
+    metadata: MetadataConfig,
+    #[serde(default)]
+    logos: LogoConfig,
+    #[serde(default)]
+    clones: CloneConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileWebhooks {
+    launches: Option<String>,
+    alerts: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FilePricing {
+    url: Option<String>,
+    pointer: Option<String>,
+    fixed_rate: Option<f64>,
+    ttl_secs: Option<u64>,
+}
+
+/// Optional parts of the pipeline, all enabled by default.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Features {
+    /// Replay transactions missed while the websocket was down
+    pub backfill: bool,
+    /// Fetch and track bonding curve state for traded mints
+    pub curve_tracking: bool,
+    /// Show USD values next to SOL amounts
+    pub usd_pricing: bool,
+    /// Look up the creator's earlier launches for the create embed
+    pub creator_history: bool,
+    /// Hash launch images and flag launches reusing an earlier one's
+    pub logo_matching: bool,
+    /// Flag launches whose name or symbol copies a watchlisted or recent one
+    pub clone_detection: bool,
+    /// Persist events to the SQLite database
+    pub storage: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            backfill: true,
+            curve_tracking: true,
+            usd_pricing: true,
+            creator_history: true,
+            logo_matching: true,
+            clone_detection: true,
+            storage: true,
+        }
+    }
+}
+
+impl Features {
+    fn with_overrides(self, flags: &FeatureFlags) -> Self {
+        Self {
+            backfill: flags.backfill.unwrap_or(self.backfill),
+            curve_tracking: flags.curve_tracking.unwrap_or(self.curve_tracking),
+            usd_pricing: flags.usd_pricing.unwrap_or(self.usd_pricing),
+            creator_history: flags.creator_history.unwrap_or(self.creator_history),
+            logo_matching: flags.logo_matching.unwrap_or(self.logo_matching),
+            clone_detection: flags.clone_detection.unwrap_or(self.clone_detection),
+            storage: flags.storage.unwrap_or(self.storage),
+        }
+    }
+}
+
+/// Alert rules, posted to the alerts webhook.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Alerts {
+    pub launch: LaunchFilter,
+    pub dev_sell: DevSellAlert,
+    pub whale: WhaleAlert,
+    pub graduation: GraduationAlert,
+}
+
+/// Which launches are posted to the launches webhook, by the creator's
+/// reputation. Launches whose creator couldn't be looked up are always posted.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LaunchFilter {
+    pub min_creator_score: f64,
+    pub exclude_flags: Vec<CreatorFlag>,
+}
+
+impl LaunchFilter {
+    pub fn allows(&self, reputation: &Reputation) -> bool {
+        reputation.score >= self.min_creator_score &&
+            !self.exclude_flags.iter().any(|flag| reputation.has_flag(*flag))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DevSellAlert {
+    pub enabled: bool,
+    /// Minimum share of the creator's position sold in one trade, in
+    /// percent; 0 alerts on every sell
+    pub min_percent: f64,
+}
+
+impl Default for DevSellAlert {
+    fn default() -> Self {
+        Self { enabled: true, min_percent: 0.0 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WhaleAlert {
+    pub enabled: bool,
+    /// Minimum SOL size of a single buy or sell
+    pub min_sol: f64,
+    /// Thresholds for specific mints, overriding `min_sol`
+    pub per_mint: HashMap<String, f64>,
+}
+
+impl WhaleAlert {
+    /// SOL threshold for trades in `mint`.
+    pub fn threshold(&self, mint: &Pubkey) -> f64 {
+        self.per_mint.get(&mint.to_string()).copied().unwrap_or(self.min_sol)
+    }
+}
+
+impl Default for WhaleAlert {
+    fn default() -> Self {
+        Self { enabled: true, min_sol: 10.0, per_mint: HashMap::new() }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraduationAlert {
+    pub enabled: bool,
+}
+
+impl Default for GraduationAlert {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Thresholds behind the creator reputation flags.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReputationConfig {
+    /// A dev sell this soon after launch counts as a fast dump
+    pub fast_dump_minutes: u64,
+    /// Earlier launches that make a creator a serial launcher
+    pub serial_launcher_count: usize,
+    /// Launches whose last trade came this soon after launch are short-lived
+    pub short_lived_minutes: u64,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self { fast_dump_minutes: 10, serial_launcher_count: 5, short_lived_minutes: 60 }
+    }
+}
+
+/// Where and how off-chain token metadata is fetched.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetadataConfig {
+    /// Gateways tried for IPFS URIs, in order
+    pub ipfs_gateways: Vec<String>,
+    /// Gateways tried for Arweave URIs, in order
+    pub arweave_gateways: Vec<String>,
+    /// How long a request may be outstanding before the next gateway is
+    /// tried alongside it
+    pub hedge_delay_ms: u64,
+    pub request_timeout_secs: u64,
+    /// Responses larger than this are rejected
+    pub max_bytes: usize,
+    /// Resolved URIs kept in memory
+    pub cache_size: usize,
+    /// How long a URI no gateway answered for is not retried
+    pub failure_ttl_secs: u64,
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            ipfs_gateways: vec![
+                "https://ipfs.io/ipfs".to_string(),
+                "https://dweb.link/ipfs".to_string(),
+                "https://gateway.pinata.cloud/ipfs".to_string()
+            ],
+            arweave_gateways: vec!["https://arweave.net".to_string()],
+            hedge_delay_ms: 1500,
+            request_timeout_secs: 10,
+            max_bytes: 1024 * 1024,
+            cache_size: 10_000,
+            failure_ttl_secs: 60,
+        }
+    }
+}
+
+/// Duplicate-logo detection, see [`Features::logo_matching`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogoConfig {
+    /// Images whose 64-bit hashes differ in at most this many bits match
+    pub max_distance: u32,
+    /// Images larger than this are not downloaded
+    pub max_bytes: usize,
+    /// Earlier launches' images compared against
+    pub recent_launches: usize,
+}
+
+impl Default for LogoConfig {
+    fn default() -> Self {
+        Self { max_distance: 6, max_bytes: 5 * 1024 * 1024, recent_launches: 20_000 }
+    }
+}
+
+/// Name and symbol clone detection, see [`Features::clone_detection`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CloneConfig {
+    /// Names and tickers launches are always compared against
+    pub watchlist: Vec<String>,
+    /// Earlier launches compared against
+    pub recent_launches: usize,
+    /// Share of characters two normalized names must have in common
+    /// (by edit distance) to match, from 0 to 1
+    pub min_similarity: f64,
+}
+
+impl Default for CloneConfig {
+    fn default() -> Self {
+        Self { watchlist: Vec::new(), recent_launches: 2000, min_similarity: 0.85 }
+    }
+}
+
+/// Worker pool that enriches events off the websocket loop.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PipelineConfig {
+    pub workers: usize,
+    /// Jobs that can wait for a worker before ingest has to wait too
+    pub queue_capacity: usize,
+    pub timeouts: StageTimeouts,
+}
+
+impl PipelineConfig {
+    pub fn timeout(&self, stage: Stage) -> Duration {
+        let timeouts = &self.timeouts;
+        Duration::from_secs(match stage {
+            Stage::Curve => timeouts.curve_secs,
+            Stage::Metadata => timeouts.metadata_secs,
+            Stage::OnchainMetadata => timeouts.onchain_metadata_secs,
+            Stage::Image => timeouts.image_secs,
+            Stage::CreatorHistory => timeouts.creator_history_secs,
+            Stage::Pricing => timeouts.pricing_secs,
+            Stage::Webhook => timeouts.webhook_secs,
+        })
+    }
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self { workers: 8, queue_capacity: 1024, timeouts: StageTimeouts::default() }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StageTimeouts {
+    pub curve_secs: u64,
+    pub metadata_secs: u64,
+    pub onchain_metadata_secs: u64,
+    pub image_secs: u64,
+    pub creator_history_secs: u64,
+    pub pricing_secs: u64,
+    pub webhook_secs: u64,
+}
+
+impl Default for StageTimeouts {
+    fn default() -> Self {
+        Self {
+            curve_secs: 10,
+            metadata_secs: 15,
+            onchain_metadata_secs: 10,
+            image_secs: 15,
+            creator_history_secs: 30,
+            pricing_secs: 5,
+            webhook_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Webhooks {
+    pub launches: String,
+    pub alerts: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PricingConfig {
+    pub source: PriceSource,
+    pub ttl: Duration,
+}
+
+/// Validated monitor configuration.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub rpc_url: String,
+    pub ws_url: String,
+    pub program_id: Pubkey,
+    pub commitment: Commitment,
+    pub database: PathBuf,
+    pub webhooks: Webhooks,
+    pub features: Features,
+    pub pricing: PricingConfig,
+    pub alerts: Alerts,
+    pub pipeline: PipelineConfig,
+    pub reputation: ReputationConfig,
+    pub metadata: MetadataConfig,
+    pub logos: LogoConfig,
+    pub clones: CloneConfig,
+}
+
+impl Config {
+    /// Parses the command line, reads the config file and validates the result.
+    pub fn load() -> anyhow::Result<Self> {
+        Self::from_cli(Cli::parse())
+    }
+
+    pub fn from_cli(cli: Cli) -> anyhow::Result<Self> {
+        let file = match &cli.config {
+            Some(path) => read_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => read_file(Path::new(DEFAULT_CONFIG_PATH))?,
+            None => FileConfig::default(),
+        };
+
+        let rpc_url = cli.rpc_url
+            .or(file.rpc_url)
+            .context("`rpc_url` is not set (config file, --rpc-url or MOONSHOT_RPC_URL)")?;
+        validate_url("rpc_url", &rpc_url, &["http", "https"])?;
+
+        let ws_url = cli.ws_url
+            .or(file.ws_url)
+            .context("`ws_url` is not set (config file, --ws-url or MOONSHOT_WS_URL)")?;
+        validate_url("ws_url", &ws_url, &["ws", "wss"])?;
+
+        let program_id = cli.program_id.or(file.program_id).unwrap_or(MOONSHOT_PROGRAM_ID.to_string());
+        let program_id = Pubkey::from_str(&program_id).map_err(|_|
+            anyhow::anyhow!("`program_id` is not a valid pubkey: {}", program_id)
+        )?;
+
+        let launches = cli.launch_webhook
+            .or(file.webhooks.launches)
+            .context(
+                "`webhooks.launches` is not set (config file, --launch-webhook or MOONSHOT_LAUNCH_WEBHOOK)"
+            )?;
+        validate_url("webhooks.launches", &launches, &["https", "http"])?;
+        let alerts = cli.alert_webhook.or(file.webhooks.alerts).unwrap_or(launches.clone());
+        validate_url("webhooks.alerts", &alerts, &["https", "http"])?;
+
+        let source = match (file.pricing.fixed_rate, file.pricing.url) {
+            (Some(_), Some(_)) => anyhow::bail!("`pricing.fixed_rate` and `pricing.url` are mutually exclusive"),
+            (Some(rate), None) => {
+                if rate.is_nan() || rate <= 0.0 {
+                    anyhow::bail!("`pricing.fixed_rate` must be positive, got {}", rate);
+                }
+                PriceSource::Fixed(rate)
+            }
+            (None, Some(url)) => {
+                validate_url("pricing.url", &url, &["https", "http"])?;
+                let pointer = file.pricing.pointer.context("`pricing.pointer` is required with `pricing.url`")?;
+                if !pointer.starts_with('/') {
+                    anyhow::bail!("`pricing.pointer` must be a JSON pointer like `/solana/usd`, got {}", pointer);
+                }
+                PriceSource::Http { url, pointer }
+            }
+            (None, None) => PriceSource::coingecko(),
+        };
+
+        let min_percent = file.alerts.dev_sell.min_percent;
+        if !(0.0..=100.0).contains(&min_percent) {
+            anyhow::bail!("`alerts.dev_sell.min_percent` must be between 0 and 100, got {}", min_percent);
+        }
+        let min_creator_score = file.alerts.launch.min_creator_score;
+        if !(0.0..=100.0).contains(&min_creator_score) {
+            anyhow::bail!("`alerts.launch.min_creator_score` must be between 0 and 100, got {}", min_creator_score);
+        }
+        if file.reputation.serial_launcher_count == 0 {
+            anyhow::bail!("`reputation.serial_launcher_count` must be at least 1");
+        }
+        let whale = &file.alerts.whale;
+        validate_threshold("alerts.whale.min_sol", whale.min_sol)?;
+        for (mint, threshold) in &whale.per_mint {
+            Pubkey::from_str(mint).map_err(|_|
+                anyhow::anyhow!("`alerts.whale.per_mint` key is not a valid pubkey: {}", mint)
+            )?;
+            validate_threshold(&format!("alerts.whale.per_mint.{}", mint), *threshold)?;
+        }
+
+        let metadata = &file.metadata;
+        for gateway in &metadata.ipfs_gateways {
+            validate_url("metadata.ipfs_gateways", gateway, &["https", "http"])?;
+        }
+        for gateway in &metadata.arweave_gateways {
+            validate_url("metadata.arweave_gateways", gateway, &["https", "http"])?;
+        }
+        if metadata.request_timeout_secs == 0 {
+            anyhow::bail!("`metadata.request_timeout_secs` must be at least 1");
+        }
+        if metadata.max_bytes == 0 {
+            anyhow::bail!("`metadata.max_bytes` must be at least 1");
+        }
+
+        if file.logos.max_distance > 32 {
+            anyhow::bail!("`logos.max_distance` must be at most 32, got {}", file.logos.max_distance);
+        }
+        if file.logos.max_bytes == 0 {
+            anyhow::bail!("`logos.max_bytes` must be at least 1");
+        }
+        if file.logos.recent_launches == 0 {
+            anyhow::bail!("`logos.recent_launches` must be at least 1");
+        }
+        if file.clones.recent_launches == 0 {
+            anyhow::bail!("`clones.recent_launches` must be at least 1");
+        }
+
+        let min_similarity = file.clones.min_similarity;
+        if !(min_similarity > 0.0 && min_similarity <= 1.0) {
+            anyhow::bail!("`clones.min_similarity` must be above 0 and at most 1, got {}", min_similarity);
+        }
+
+        let pipeline = file.pipeline;
+        if pipeline.workers == 0 {
+            anyhow::bail!("`pipeline.workers` must be at least 1");
+        }
+        if pipeline.queue_capacity == 0 {
+            anyhow::bail!("`pipeline.queue_capacity` must be at least 1");
+        }
+        if let Some(stage) = Stage::ALL.into_iter().find(|stage| pipeline.timeout(*stage).is_zero()) {
+            anyhow::bail!("`pipeline.timeouts.{}_secs` must be at least 1", stage.name());
+        }
+
+        Ok(Config {
+            rpc_url,
+            ws_url,
+            program_id,
+            commitment: cli.commitment.or(file.commitment).unwrap_or_default(),
+            database: cli.database.or(file.database).unwrap_or(PathBuf::from(DEFAULT_DATABASE_PATH)),
+            webhooks: Webhooks { launches, alerts },
+            features: file.features.with_overrides(&cli.features),
+            pricing: PricingConfig {
+                source,
+                ttl: Duration::from_secs(file.pricing.ttl_secs.unwrap_or(60)),
+            },
+            alerts: file.alerts,
+            pipeline,
+            reputation: file.reputation,
+            metadata: file.metadata,
+            logos: file.logos,
+            clones: file.clones,
+        })
+    }
+}
+
+fn validate_threshold(key: &str, sol: f64) -> anyhow::Result<()> {
+    if sol.is_nan() || sol < 0.0 {
+        anyhow::bail!("`{}` must be a non-negative SOL amount, got {}", key, sol);
+    }
+    Ok(())
+}
+
+fn read_file(path: &Path) -> anyhow::Result<FileConfig> {
+    let contents = std::fs
+        ::read_to_string(path)
+        .with_context(|| format!("failed to read config file {}", path.display()))?;
+    toml::from_str(&contents).with_context(|| format!("invalid config file {}", path.display()))
+}
+
+fn validate_url(name: &str, value: &str, schemes: &[&str]) -> anyhow::Result<()> {
+    let url = Url::parse(value).with_context(|| format!("`{}` is not a valid URL: {}", name, value))?;
+    if !schemes.contains(&url.scheme()) {
+        anyhow::bail!("`{}` must use one of {:?}, got {}", name, schemes, url.scheme());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{ CommandFactory, FromArgMatches };
+
+    use super::*;
+
+    /// Parses `args` like `Cli::try_parse_from`, but ignoring any MOONSHOT_*
+    /// variables set where the tests run.
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        let command = Cli::command().mut_args(|arg| arg.env(None));
+        Cli::from_arg_matches(&command.try_get_matches_from(args)?)
+    }
+
+    #[test]
+    fn feature_flags_override_the_file() {
+        let cli = parse(&["moonshot-monitor", "--backfill", "false", "--logo-matching=false"]).unwrap();
+        let file = Features { curve_tracking: false, ..Features::default() };
+
+        let features = file.with_overrides(&cli.features);
+        assert!(!features.backfill);
+        assert!(!features.logo_matching);
+        assert!(!features.curve_tracking);
+        assert!(features.creator_history && features.storage);
+    }
+
+    #[test]
+    fn feature_flags_take_booleans() {
+        assert!(parse(&["moonshot-monitor", "--storage", "off"]).is_err());
+        let cli = parse(&["moonshot-monitor", "--storage", "true"]).unwrap();
+        let features = Features { storage: false, ..Features::default() }.with_overrides(&cli.features);
+        assert!(features.storage);
+    }
+
+    #[test]
+    fn ignores_the_environment() {
+        let cli = parse(&["moonshot-monitor"]).unwrap();
+        assert!(cli.rpc_url.is_none() && cli.config.is_none());
+        assert!(cli.features.backfill.is_none() && cli.features.storage.is_none());
+    }
+
+    #[test]
+    fn rejects_an_empty_clone_window() {
+        let path = std::env::temp_dir().join(format!("moonshot-monitor-test-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "rpc_url = \"https://rpc.example\"\nws_url = \"wss://ws.example\"\n\
+             [webhooks]\nlaunches = \"https://discord.example/webhook\"\n\
+             [clones]\nrecent_launches = 0\n"
+        ).unwrap();
+        let cli = parse(&["moonshot-monitor", "--config", path.to_str().unwrap()]).unwrap();
+        let result = Config::from_cli(cli);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.unwrap_err().to_string().contains("`clones.recent_launches`"));
+    }
+}
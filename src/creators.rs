@@ -0,0 +1,570 @@
+use std::{ collections::HashMap, fmt, str::FromStr, sync::{ Arc, Mutex }, time::{ Duration, Instant } };
+
+use futures_util::{ stream, StreamExt };
+use serde::{ Deserialize, Serialize };
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{ commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature };
+
+use crate::{
+    backfill::{ fetch_notification, history_commitment },
+    event::{ scan_pump_events, EventEnvelope, MoonEvent, TransactionNotification },
+    storage::{ CreatorScan, Storage },
+    utlis::user_data::{ get_token_metadata_batch, OnchainMetadata },
+};
+
+/// `getSignaturesForAddress` page size (the RPC maximum).
+const PAGE_LIMIT: usize = 1000;
+/// Signatures listed by a creator's first scan; older history of busier
+/// wallets isn't covered.
+const MAX_SIGNATURES: usize = 2000;
+/// Transactions fetched in parallel while scanning.
+const FETCH_CONCURRENCY: usize = 8;
+/// Transactions fetched between saves of the scan's progress.
+const SCAN_CHUNK: usize = 100;
+/// Share of the creator's bought tokens sold at which a launch counts as abandoned.
+const ABANDONED_SOLD_RATIO: f64 = 0.9;
+
+/// How a creator's earlier launch turned out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchOutcome {
+    /// The curve completed and the token moved to a DEX
+    Migrated,
+    /// The creator sold (nearly) everything they bought
+    Abandoned,
+    /// Still on the curve with the creator holding
+    Active,
+}
+
+impl fmt::Display for LaunchOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            LaunchOutcome::Migrated => "migrated",
+            LaunchOutcome::Abandoned => "dev sold out",
+            LaunchOutcome::Active => "active",
+        })
+    }
+}
+
+/// A Moonshot token launched by the creator, with the creator's own trades
+/// in it as found in their transaction history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatorLaunch {
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub total_supply: u64,
+    pub decimals: u8,
+    /// Raw tokens the creator bought, dev buy included
+    pub dev_bought: u64,
+    pub dev_sold: u64,
+    pub dev_sold_lamports: u64,
+    pub first_dev_sell_time: Option<i64>,
+    /// Highest SOL per whole token seen, from the creator's trades and any
+    /// stored trades of the mint
+    pub peak_price: Option<f64>,
+    /// Latest stored trade by anyone but the creator. The creator's own
+    /// trades don't count: a dev buy alone says nothing about how long the
+    /// token was traded.
+    pub last_activity_time: Option<i64>,
+    /// Known from migrations the monitor observed or stored
+    pub migrated: bool,
+    /// Current on-chain metadata, if it could be read; not persisted
+    #[serde(skip)]
+    pub metadata: Option<OnchainMetadata>,
+}
+
+impl CreatorLaunch {
+    pub fn outcome(&self) -> LaunchOutcome {
+        if self.migrated {
+            LaunchOutcome::Migrated
+        } else if
+            self.dev_bought > 0 &&
+            (self.dev_sold as f64) >= (self.dev_bought as f64) * ABANDONED_SOLD_RATIO
+        {
+            LaunchOutcome::Abandoned
+        } else {
+            LaunchOutcome::Active
+        }
+    }
+
+    pub fn peak_market_cap_sol(&self) -> Option<f64> {
+        Some(self.peak_price? * ((self.total_supply as f64) / (10f64).powi(self.decimals as i32)))
+    }
+
+    /// Seconds between the launch and the last trade by someone other than
+    /// the creator; `None` without stored trades from other wallets.
+    pub fn lifetime_secs(&self) -> Option<i64> {
+        Some((self.last_activity_time? - self.block_time?).max(0))
+    }
+
+    /// Seconds between the launch and the creator's first sell.
+    pub fn dev_first_sell_secs(&self) -> Option<i64> {
+        Some((self.first_dev_sell_time? - self.block_time?).max(0))
+    }
+
+    fn record_price(&mut self, price: Option<f64>) {
+        if let Some(price) = price.filter(|price| *price > 0.0) {
+            self.peak_price = Some(self.peak_price.map_or(price, |peak| peak.max(price)));
+        }
+    }
+}
+
+/// Moonshot launches signed by one creator, oldest first.
+#[derive(Debug, Clone)]
+pub struct CreatorHistory {
+    pub creator: Pubkey,
+    pub launches: Vec<CreatorLaunch>,
+    /// Newest signature scanned, with every older one in the scan fetched;
+    /// the next scan stops there
+    newest_signature: Option<String>,
+    /// `false` if the wallet has more history than a scan covers
+    pub complete: bool,
+}
+
+impl CreatorHistory {
+    fn new(creator: Pubkey) -> Self {
+        Self { creator, launches: Vec::new(), newest_signature: None, complete: true }
+    }
+
+    pub fn launch_count(&self) -> usize {
+        self.launches.len()
+    }
+
+    pub fn mints(&self) -> impl Iterator<Item = &Pubkey> {
+        self.launches.iter().map(|launch| &launch.mint)
+    }
+
+    /// Number of launches per outcome.
+    pub fn outcome_count(&self, outcome: LaunchOutcome) -> usize {
+        self.launches
+            .iter()
+            .filter(|launch| launch.outcome() == outcome)
+            .count()
+    }
+
+    /// Launches other than `mint`, e.g. to list a creator's earlier tokens
+    /// next to a new one.
+    pub fn others(&self, mint: &Pubkey) -> impl Iterator<Item = &CreatorLaunch> {
+        let mint = *mint;
+        self.launches.iter().filter(move |launch| launch.mint != mint)
+    }
+
+    fn apply(&mut self, envelope: &EventEnvelope) {
+        let context = &envelope.context;
+        match &envelope.event {
+            MoonEvent::CreateEvent(create) if create.sender == self.creator => {
+                if self.launches.iter().any(|launch| launch.mint == create.mint) {
+                    return;
+                }
+                self.launches.push(CreatorLaunch {
+                    mint: create.mint,
+                    name: create.name.clone(),
+                    symbol: create.symbol.clone(),
+                    signature: context.signature.clone(),
+                    slot: context.slot,
+                    block_time: context.block_time,
+                    total_supply: create.total_supply,
+                    decimals: create.decimals,
+                    dev_bought: 0,
+                    dev_sold: 0,
+                    dev_sold_lamports: 0,
+                    first_dev_sell_time: None,
+                    peak_price: None,
+                    last_activity_time: None,
+                    migrated: false,
+                    metadata: None,
+                });
+                self.launches.sort_by_key(|launch| launch.slot);
+            }
+            MoonEvent::BuyEvent(buy) if buy.sender == self.creator => {
+                if let Some(launch) = self.launch_mut(&buy.mint) {
+                    launch.dev_bought += buy.execution.map_or(buy.amount, |execution| execution.token_amount);
+                    launch.record_price(buy.execution.map(|execution| execution.price));
+                }
+            }
+            MoonEvent::SellEvent(sell) if sell.sender == self.creator => {
+                if let Some(launch) = self.launch_mut(&sell.mint) {
+                    launch.dev_sold += sell.execution.map_or(sell.amount, |execution| execution.token_amount);
+                    launch.dev_sold_lamports += sell.execution.map_or(
+                        sell.collateral_amount,
+                        |execution| execution.sol_lamports
+                    );
+                    if launch.first_dev_sell_time.is_none() {
+                        launch.first_dev_sell_time = context.block_time;
+                    }
+                    launch.record_price(sell.execution.map(|execution| execution.price));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn launch_mut(&mut self, mint: &Pubkey) -> Option<&mut CreatorLaunch> {
+        self.launches.iter_mut().find(|launch| launch.mint == *mint)
+    }
+}
+
+/// Finds creators' Moonshot launches from their own transaction history and
+/// caches it per creator (in memory, and in the database when enabled);
+/// later lookups only scan signatures newer than the last one seen.
+pub struct CreatorHistoryService {
+    program_id: Pubkey,
+    storage: Option<Arc<Storage>>,
+    histories: Mutex<HashMap<Pubkey, CreatorHistory>>,
+    /// Held while a creator is scanned, so concurrent lookups of the same
+    /// creator wait for the running scan and then continue from its result.
+    scans: Mutex<HashMap<Pubkey, Arc<tokio::sync::Mutex<()>>>>,
+    /// Time a lookup may spend fetching transactions; the rest of the scan
+    /// is picked up by the next lookup
+    scan_budget: Duration,
+    /// Commitment launch metadata and signatures (at least `confirmed`)
+    /// are read at, the one launches are announced at
+    commitment: CommitmentConfig,
+}
+
+impl CreatorHistoryService {
+    pub fn new(
+        program_id: Pubkey,
+        storage: Option<Arc<Storage>>,
+        scan_budget: Duration,
+        commitment: CommitmentConfig
+    ) -> Self {
+        Self {
+            program_id,
+            storage,
+            histories: Mutex::new(HashMap::new()),
+            scans: Mutex::new(HashMap::new()),
+            scan_budget,
+            commitment,
+        }
+    }
+
+    /// Cached history of `creator`, if it was looked up before.
+    pub fn cached(&self, creator: &Pubkey) -> Option<CreatorHistory> {
+        if let Some(history) = self.histories.lock().unwrap().get(creator) {
+            return Some(history.clone());
+        }
+        let scan = match self.storage.as_ref()?.creator_scan(&creator.to_string()) {
+            Ok(scan) => scan?,
+            Err(e) => {
+                eprintln!("Failed to load creator scan of {}: {:#}", creator, e);
+                return None;
+            }
+        };
+        let launches = match serde_json::from_str(&scan.launches) {
+            Ok(launches) => launches,
+            Err(e) => {
+                eprintln!("Ignoring unreadable creator scan of {}: {}", creator, e);
+                return None;
+            }
+        };
+        Some(CreatorHistory {
+            creator: *creator,
+            launches,
+            newest_signature: scan.newest_signature,
+            complete: scan.complete,
+        })
+    }
+
+    /// Keeps the scan progress in `history`, so a lookup cut short (e.g. by
+    /// the stage timeout) isn't repeated from scratch.
+    fn save(&self, history: &CreatorHistory) {
+        self.histories.lock().unwrap().insert(history.creator, history.clone());
+        let Some(storage) = &self.storage else {
+            return;
+        };
+        let scan = CreatorScan {
+            newest_signature: history.newest_signature.clone(),
+            complete: history.complete,
+            launches: match serde_json::to_string(&history.launches) {
+                Ok(launches) => launches,
+                Err(e) => {
+                    eprintln!("Failed to serialize creator scan of {}: {}", history.creator, e);
+                    return;
+                }
+            },
+        };
+        if let Err(e) = storage.save_creator_scan(&history.creator.to_string(), &scan) {
+            eprintln!("Failed to store creator scan of {}: {:#}", history.creator, e);
+        }
+    }
+
+    /// History of `creator`, brought up to date with their newest
+    /// transactions as far as `scan_budget` allows; if it runs out, or a
+    /// transaction can't be fetched, the result is marked incomplete and the
+    /// next lookup resumes where this one stopped. Lookups of a creator
+    /// already being scanned wait for that scan instead of repeating it.
+    pub async fn history(&self, client: &RpcClient, creator: &Pubkey) -> anyhow::Result<CreatorHistory> {
+        let scan = self.scans.lock().unwrap().entry(*creator).or_default().clone();
+        let result = {
+            let _running = scan.lock().await;
+            self.scan(client, creator).await
+        };
+        drop(scan);
+        self.scans.lock().unwrap().retain(|_, scan| Arc::strong_count(scan) > 1);
+        result
+    }
+
+    async fn scan(&self, client: &RpcClient, creator: &Pubkey) -> anyhow::Result<CreatorHistory> {
+        let deadline = Instant::now() + self.scan_budget;
+        let mut history = self.cached(creator).unwrap_or_else(|| CreatorHistory::new(*creator));
+        let until = history.newest_signature.as_deref().map(Signature::from_str).transpose()?;
+
+        let mut finished = true;
+        let (statuses, reached) = self.signatures(client, creator, until, deadline).await?;
+        let statuses = match until {
+            None => {
+                history.complete = reached;
+                statuses
+            }
+            Some(_) if !reached => {
+                // Scanning just the newest would skip the rest for good, so
+                // all of them are left to the next lookup
+                eprintln!("Creator {} has too many new transactions to list within the scan budget", creator);
+                finished = false;
+                Vec::new()
+            }
+            Some(_) => statuses,
+        };
+
+        // Oldest first, so launches exist before the trades in them are
+        // applied, and so `newest_signature` only moves past fetched ones
+        let statuses: Vec<_> = statuses.into_iter().rev().collect();
+        for chunk in statuses.chunks(SCAN_CHUNK) {
+            if Instant::now() >= deadline {
+                finished = false;
+                break;
+            }
+            let notifications: Vec<_> = stream
+                ::iter(chunk.to_vec())
+                .map(|status| async move {
+                    if status.err.is_some() {
+                        // Failed transactions carry no events
+                        return Ok(None);
+                    }
+                    fetch_notification(client, &status.signature, status.slot).await
+                })
+                .buffered(FETCH_CONCURRENCY)
+                .collect().await;
+            for (status, notification) in chunk.iter().zip(notifications) {
+                let notification = match notification {
+                    Ok(notification) => notification,
+                    Err(e) => {
+                        eprintln!("Failed to fetch transaction {}: {:#}", status.signature, e);
+                        finished = false;
+                        break;
+                    }
+                };
+                // Most of a wallet's transactions don't involve Moonshot; they
+                // decode to no events without touching the parse error counters
+                let notification = notification.and_then(|notification|
+                    serde_json::from_value::<TransactionNotification>(notification).ok()
+                );
+                if let Some(notification) = notification {
+                    for envelope in scan_pump_events(notification, &self.program_id) {
+                        history.apply(&envelope);
+                    }
+                }
+                history.newest_signature = Some(status.signature.clone());
+            }
+            self.save(&history);
+            if !finished {
+                break;
+            }
+        }
+
+        if let Some(storage) = &self.storage {
+            for launch in history.launches.iter_mut() {
+                let mint = launch.mint.to_string();
+                if !launch.migrated {
+                    launch.migrated = storage.migrated(&mint).unwrap_or(false);
+                }
+                if let Ok(Some(activity)) = storage.mint_activity(&mint, &creator.to_string()) {
+                    launch.record_price(activity.peak_price);
+                    launch.last_activity_time = activity.last_block_time;
+                }
+            }
+        }
+
+        let missing: Vec<Pubkey> = history.launches
+            .iter()
+            .filter(|launch| launch.metadata.is_none())
+            .map(|launch| launch.mint)
+            .collect();
+        if !missing.is_empty() {
+            let results = get_token_metadata_batch(client, &missing, self.commitment).await;
+            for (mint, result) in missing.iter().zip(results) {
+                match result {
+                    Ok(metadata) => {
+                        if let Some(launch) = history.launch_mut(mint) {
+                            launch.metadata = Some(metadata);
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to read metadata of {}: {:#}", mint, e),
+                }
+            }
+        }
+
+        self.histories.lock().unwrap().insert(*creator, history.clone());
+        if !finished {
+            history.complete = false;
+        }
+        Ok(history)
+    }
+
+    /// Marks a migrated mint in whichever cached history contains it.
+    pub fn record_migration(&self, mint: &Pubkey) {
+        for history in self.histories.lock().unwrap().values_mut() {
+            if let Some(launch) = history.launch_mut(mint) {
+                launch.migrated = true;
+            }
+        }
+    }
+
+    /// The creator's signatures newer than `until`, newest first, and
+    /// whether they reach back that far. A first scan lists at most
+    /// `MAX_SIGNATURES`; an incremental one pages down to `until` unless
+    /// `deadline` passes first.
+    async fn signatures(
+        &self,
+        client: &RpcClient,
+        creator: &Pubkey,
+        until: Option<Signature>,
+        deadline: Instant
+    ) -> anyhow::Result<(Vec<RpcConfirmedTransactionStatusWithSignature>, bool)> {
+        let mut statuses = Vec::new();
+        let mut before = None;
+        loop {
+            let capped = match until {
+                None => statuses.len() >= MAX_SIGNATURES,
+                Some(_) => Instant::now() >= deadline,
+            };
+            if capped {
+                return Ok((statuses, false));
+            }
+            let page = client.get_signatures_for_address_with_config(
+                creator,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(PAGE_LIMIT),
+                    commitment: Some(history_commitment(self.commitment)),
+                }
+            ).await?;
+            let Some(last) = page.last() else {
+                return Ok((statuses, true));
+            };
+            before = Some(Signature::from_str(&last.signature)?);
+            let page_len = page.len();
+            statuses.extend(page);
+            if page_len < PAGE_LIMIT {
+                return Ok((statuses, true));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::ReputationConfig,
+        event::{ BuyEvent, CreateEvent, EventContext, TradeExecution },
+        reputation::{ CreatorFlag, Reputation },
+    };
+
+    const LAUNCH_TIME: i64 = 1_700_000_000;
+
+    fn envelope(signature: &str, slot: u64, index: usize, event: MoonEvent) -> EventEnvelope {
+        EventEnvelope {
+            context: EventContext {
+                signature: signature.to_string(),
+                slot,
+                block_time: Some(LAUNCH_TIME + (slot as i64)),
+                fee_payer: Pubkey::default(),
+                fee: 5000,
+                compute_units: 0,
+                success: true,
+                index,
+            },
+            event,
+        }
+    }
+
+    /// A launch transaction: create plus the dev buy, as the creator signs it.
+    fn launch_with_dev_buy(history: &mut CreatorHistory, slot: u64) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        let curve_account = Pubkey::new_unique();
+        let signature = format!("launch-{}", slot);
+        let buy = BuyEvent {
+            amount: 10_000_000_000_000,
+            collateral_amount: 1_000_000_000,
+            slippage_bps: 100,
+            sender: history.creator,
+            curve_account,
+            mint,
+            execution: Some(TradeExecution {
+                sol_lamports: 1_000_000_000,
+                token_amount: 10_000_000_000_000,
+                token_balance_after: 10_000_000_000_000,
+                decimals: 9,
+                price: 0.0001,
+            }),
+        };
+        let create = CreateEvent {
+            name: "Token".to_string(),
+            symbol: "TKN".to_string(),
+            uri: String::new(),
+            sender: history.creator,
+            curve_account,
+            mint,
+            total_supply: 1_000_000_000_000_000_000,
+            decimals: 9,
+            buy_event: Some(buy.clone()),
+        };
+        history.apply(&envelope(&signature, slot, 0, MoonEvent::CreateEvent(create)));
+        history.apply(&envelope(&signature, slot, 1, MoonEvent::BuyEvent(buy)));
+        mint
+    }
+
+    #[test]
+    fn dev_buy_alone_has_no_lifetime() {
+        let mut history = CreatorHistory::new(Pubkey::new_unique());
+        let mint = launch_with_dev_buy(&mut history, 100);
+
+        let launch = history.launches.iter().find(|launch| launch.mint == mint).unwrap();
+        assert_eq!(launch.dev_bought, 10_000_000_000_000);
+        assert_eq!(launch.lifetime_secs(), None);
+    }
+
+    #[test]
+    fn creator_with_only_dev_buys_is_not_short_lived() {
+        let mut history = CreatorHistory::new(Pubkey::new_unique());
+        for slot in [100, 200, 300] {
+            launch_with_dev_buy(&mut history, slot);
+        }
+        let new_mint = launch_with_dev_buy(&mut history, 400);
+
+        let reputation = Reputation::score(&history, &new_mint, &ReputationConfig::default());
+        assert_eq!(reputation.launches, 3);
+        assert!(!reputation.has_flag(CreatorFlag::ShortLived), "{}", reputation);
+        assert!(!reputation.has_flag(CreatorFlag::FastDumper), "{}", reputation);
+    }
+
+    #[test]
+    fn lifetime_runs_to_last_trade_by_others() {
+        let mut history = CreatorHistory::new(Pubkey::new_unique());
+        let mint = launch_with_dev_buy(&mut history, 100);
+        let launch = history.launch_mut(&mint).unwrap();
+        launch.last_activity_time = Some(LAUNCH_TIME + 100 + 3600);
+
+        assert_eq!(launch.lifetime_secs(), Some(3600));
+    }
+}
@@ -0,0 +1,350 @@
+use std::{ collections::{ hash_map::Entry, HashMap }, fmt, str::FromStr, sync::Mutex };
+
+use serde::{ Deserialize, Deserializer };
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{ commitment_config::CommitmentConfig, pubkey::Pubkey };
+
+use crate::{ event::{ EventEnvelope, MoonEvent }, idl };
+
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+/// On-chain `CurveAccount` of a Moonshot token, as laid out in the IDL.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurveAccount {
+    pub total_supply: u64,
+    /// Tokens still held by the curve
+    pub curve_amount: u64,
+    #[serde(deserialize_with = "pubkey_from_str")]
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub collateral_currency: String,
+    pub curve_type: String,
+    /// Market cap, in lamports of `marketcap_currency`, at which the token migrates
+    pub marketcap_threshold: u64,
+    pub marketcap_currency: String,
+    pub migration_fee: u64,
+    pub coef_b: u32,
+    pub bump: u8,
+    pub migration_target: String,
+}
+
+fn pubkey_from_str<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+    let pubkey = String::deserialize(deserializer)?;
+    Pubkey::from_str(&pubkey).map_err(serde::de::Error::custom)
+}
+
+impl CurveAccount {
+    pub fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let (name, value) = idl::moonshot().decode_account(data)?;
+        if name != "CurveAccount" {
+            anyhow::bail!("expected CurveAccount, got {}", name);
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Fetches and decodes a curve account at `commitment`, returning it with
+    /// the slot it was read at.
+    pub async fn fetch(
+        client: &RpcClient,
+        curve_account: &Pubkey,
+        commitment: CommitmentConfig
+    ) -> anyhow::Result<(Self, u64)> {
+        let response = client.get_account_with_commitment(curve_account, commitment).await?;
+        let Some(account) = response.value else {
+            anyhow::bail!("curve account {} not found", curve_account);
+        };
+        Ok((Self::decode(&account.data)?, response.context.slot))
+    }
+}
+
+/// Live view of a token's bonding curve: the last fetched account, kept
+/// current by applying observed trades on top of it.
+#[derive(Debug, Clone)]
+pub struct CurveState {
+    pub curve_account: Pubkey,
+    pub account: CurveAccount,
+    /// Slot the account snapshot was read at; trades up to it are already included
+    pub account_slot: u64,
+    /// SOL per whole token of the most recent trade
+    pub last_price: Option<f64>,
+    pub last_trade_slot: u64,
+}
+
+impl CurveState {
+    fn scale(&self) -> f64 {
+        (10f64).powi(self.account.decimals as i32)
+    }
+
+    /// Tokens sold out of the curve so far.
+    pub fn tokens_sold(&self) -> f64 {
+        (self.account.total_supply.saturating_sub(self.account.curve_amount) as f64) / self.scale()
+    }
+
+    /// Fully diluted market cap in SOL at the last traded price.
+    pub fn market_cap_sol(&self) -> Option<f64> {
+        Some(self.last_price? * ((self.account.total_supply as f64) / self.scale()))
+    }
+
+    /// Progress towards the migration market cap, in percent (capped at 100).
+    pub fn migration_progress(&self) -> Option<f64> {
+        if self.account.marketcap_threshold == 0 {
+            return None;
+        }
+        let threshold = (self.account.marketcap_threshold as f64) / LAMPORTS_PER_SOL;
+        Some(((self.market_cap_sol()? / threshold) * 100.0).min(100.0))
+    }
+
+    fn apply(&mut self, envelope: &EventEnvelope) {
+        let (execution, is_buy) = match &envelope.event {
+            MoonEvent::BuyEvent(buy) => (buy.execution, true),
+            MoonEvent::SellEvent(sell) => (sell.execution, false),
+            MoonEvent::CreateEvent(_) | MoonEvent::Migration(_) => {
+                return;
+            }
+        };
+        let Some(execution) = execution else {
+            return;
+        };
+
+        if envelope.context.slot > self.account_slot {
+            self.account.curve_amount = if is_buy {
+                self.account.curve_amount.saturating_sub(execution.token_amount)
+            } else {
+                self.account.curve_amount.saturating_add(execution.token_amount)
+            };
+        }
+        if envelope.context.slot >= self.last_trade_slot && execution.token_amount > 0 {
+            self.last_price = Some(execution.price);
+            self.last_trade_slot = envelope.context.slot;
+        }
+    }
+}
+
+impl fmt::Display for CurveState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}): ", self.account.mint, self.account.curve_type)?;
+        match (self.last_price, self.market_cap_sol()) {
+            (Some(price), Some(market_cap)) =>
+                write!(f, "price {:.10} SOL, market cap {:.2} SOL", price, market_cap)?,
+            _ => write!(f, "no trades yet")?,
+        }
+        if let Some(progress) = self.migration_progress() {
+            write!(f, ", {:.1}% to migration", progress)?;
+        }
+        Ok(())
+    }
+}
+
+/// What [`CurveTracker::track`] did with a trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tracked {
+    /// Applied to the tracked curve
+    Applied,
+    /// Held until the in-flight fetch of the curve completes
+    Buffered,
+    /// Held; the caller should fetch the curve with [`CurveTracker::refresh`]
+    Fetch,
+}
+
+/// Per-mint curve states shared between the event loop and reporting.
+#[derive(Default)]
+pub struct CurveTracker {
+    states: Mutex<HashMap<Pubkey, CurveState>>,
+    /// Trades of mints whose curve account is being fetched, replayed on
+    /// top of the snapshot once it arrives
+    refreshing: Mutex<HashMap<Pubkey, Vec<EventEnvelope>>>,
+}
+
+impl CurveTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<CurveState> {
+        self.states.lock().unwrap().get(mint).cloned()
+    }
+
+    /// Applies a trade to the tracked curve, or holds it until the mint's
+    /// curve has been fetched.
+    pub fn track(&self, envelope: &EventEnvelope) -> Tracked {
+        let mint = match &envelope.event {
+            MoonEvent::BuyEvent(buy) => buy.mint,
+            MoonEvent::SellEvent(sell) => sell.mint,
+            MoonEvent::CreateEvent(create) => create.mint,
+            MoonEvent::Migration(migration) => migration.mint,
+        };
+        let mut states = self.states.lock().unwrap();
+        if let Some(state) = states.get_mut(&mint) {
+            state.apply(envelope);
+            return Tracked::Applied;
+        }
+        match self.refreshing.lock().unwrap().entry(mint) {
+            Entry::Occupied(mut pending) => {
+                pending.get_mut().push(envelope.clone());
+                Tracked::Buffered
+            }
+            Entry::Vacant(pending) => {
+                pending.insert(vec![envelope.clone()]);
+                Tracked::Fetch
+            }
+        }
+    }
+
+    /// Stops tracking a mint, e.g. once its curve has migrated, returning
+    /// its last state.
+    pub fn remove(&self, mint: &Pubkey) -> Option<CurveState> {
+        self.states.lock().unwrap().remove(mint)
+    }
+
+    /// Drops the trades held for `mint` after its fetch failed, so the next
+    /// trade starts another.
+    pub fn abandon_refresh(&self, mint: &Pubkey) {
+        self.refreshing.lock().unwrap().remove(mint);
+    }
+
+    /// Forgets mints whose curve hasn't been read or traded since
+    /// `min_slot`, so memory stays bounded.
+    pub fn prune(&self, min_slot: u64) {
+        self.states
+            .lock()
+            .unwrap()
+            .retain(|_, state| state.account_slot.max(state.last_trade_slot) >= min_slot);
+    }
+
+    /// Re-reads the curve account over RPC at `commitment`, which should be
+    /// the one trades are observed at, and installs it.
+    pub async fn refresh(
+        &self,
+        client: &RpcClient,
+        curve_account: &Pubkey,
+        commitment: CommitmentConfig
+    ) -> anyhow::Result<CurveState> {
+        let (account, account_slot) = CurveAccount::fetch(client, curve_account, commitment).await?;
+        Ok(self.install(*curve_account, account, account_slot))
+    }
+
+    /// Tracks a freshly read curve account, keeping the last known price,
+    /// and replays the trades held while it was fetched; those in slots
+    /// the snapshot already includes only update the price.
+    fn install(&self, curve_account: Pubkey, account: CurveAccount, account_slot: u64) -> CurveState {
+        let mut states = self.states.lock().unwrap();
+        let (last_price, last_trade_slot) = states
+            .get(&account.mint)
+            .map(|state| (state.last_price, state.last_trade_slot))
+            .unwrap_or_default();
+        let mut state = CurveState { curve_account, account, account_slot, last_price, last_trade_slot };
+        let held = self.refreshing.lock().unwrap().remove(&state.account.mint).unwrap_or_default();
+        for envelope in &held {
+            state.apply(envelope);
+        }
+        states.insert(state.account.mint, state.clone());
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{ BuyEvent, EventContext, TradeExecution };
+
+    fn state(mint: Pubkey, account_slot: u64, last_trade_slot: u64) -> CurveState {
+        CurveState {
+            curve_account: Pubkey::new_unique(),
+            account: CurveAccount {
+                total_supply: 1_000_000_000_000_000_000,
+                curve_amount: 800_000_000_000_000_000,
+                mint,
+                decimals: 9,
+                collateral_currency: "Sol".to_string(),
+                curve_type: "ConstantProductV1".to_string(),
+                marketcap_threshold: 500_000_000_000,
+                marketcap_currency: "Sol".to_string(),
+                migration_fee: 0,
+                coef_b: 25,
+                bump: 255,
+                migration_target: "Raydium".to_string(),
+            },
+            account_slot,
+            last_price: None,
+            last_trade_slot,
+        }
+    }
+
+    fn buy(mint: Pubkey, slot: u64, tokens: u64, price: f64) -> EventEnvelope {
+        EventEnvelope {
+            context: EventContext {
+                signature: format!("sig-{}", slot),
+                slot,
+                block_time: None,
+                fee_payer: Pubkey::default(),
+                fee: 5000,
+                compute_units: 0,
+                success: true,
+                index: 0,
+            },
+            event: MoonEvent::BuyEvent(BuyEvent {
+                amount: tokens,
+                collateral_amount: 0,
+                slippage_bps: 100,
+                sender: Pubkey::new_unique(),
+                curve_account: Pubkey::default(),
+                mint,
+                execution: Some(TradeExecution {
+                    sol_lamports: 0,
+                    token_amount: tokens,
+                    token_balance_after: tokens,
+                    decimals: 9,
+                    price,
+                }),
+            }),
+        }
+    }
+
+    #[test]
+    fn one_fetch_per_mint_at_a_time() {
+        let curves = CurveTracker::new();
+        let mint = Pubkey::new_unique();
+        assert_eq!(curves.track(&buy(mint, 10, 1, 0.1)), Tracked::Fetch);
+        assert_eq!(curves.track(&buy(mint, 11, 1, 0.1)), Tracked::Buffered);
+        assert_eq!(curves.track(&buy(Pubkey::new_unique(), 11, 1, 0.1)), Tracked::Fetch);
+        curves.abandon_refresh(&mint);
+        assert_eq!(curves.track(&buy(mint, 12, 1, 0.1)), Tracked::Fetch);
+    }
+
+    #[test]
+    fn replays_trades_held_during_fetch() {
+        let curves = CurveTracker::new();
+        let mint = Pubkey::new_unique();
+        // Seen before the snapshot's slot: already in it
+        curves.track(&buy(mint, 100, 1_000, 0.1));
+        curves.track(&buy(mint, 105, 2_000, 0.2));
+        // After it
+        curves.track(&buy(mint, 106, 3_000, 0.3));
+        curves.track(&buy(mint, 103, 500, 0.15));
+
+        let snapshot = state(mint, 105, 0);
+        let curve_amount = snapshot.account.curve_amount;
+        let installed = curves.install(snapshot.curve_account, snapshot.account, 105);
+        assert_eq!(installed.account.curve_amount, curve_amount - 3_000);
+        assert_eq!(installed.last_price, Some(0.3));
+        assert_eq!(installed.last_trade_slot, 106);
+
+        assert_eq!(curves.track(&buy(mint, 107, 4_000, 0.4)), Tracked::Applied);
+        assert_eq!(curves.get(&mint).unwrap().account.curve_amount, curve_amount - 7_000);
+    }
+
+    #[test]
+    fn prunes_idle_curves() {
+        let curves = CurveTracker::new();
+        let (idle, read, traded) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        for (mint, account_slot, last_trade_slot) in [(idle, 10, 15), (read, 30, 0), (traded, 10, 25)] {
+            curves.states.lock().unwrap().insert(mint, state(mint, account_slot, last_trade_slot));
+        }
+
+        curves.prune(20);
+        assert!(curves.get(&idle).is_none());
+        assert!(curves.get(&read).is_some());
+        assert!(curves.get(&traded).is_some());
+    }
+}
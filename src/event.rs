@@ -1,7 +1,11 @@
-use std::str::FromStr;
+use std::{ collections::HashMap, fmt, str::FromStr, sync::atomic::{ AtomicU64, Ordering } };
 
 use solana_sdk::pubkey::Pubkey;
 
+use crate::idl::{ self, DecodeError, DecodedInstruction };
+
+pub const MOONSHOT_PROGRAM_ID: &str = "MoonCVVNZFSYkqNXP6bxHLPL6QQJiMagDL3qcqUQTrG";
+
 use serde::{ Deserialize, Serialize };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,9 +35,40 @@ pub struct Context {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Value {
     slot: u64,
+    signature: Option<String>,
+    #[serde(rename = "blockTime")]
+    block_time: Option<i64>,
     transaction: Option<Transaction>,
 }
 
+impl TransactionNotification {
+    pub fn from_value(value: serde_json::Value) -> Result<Self, ParseError> {
+        serde_json
+            ::from_value(value)
+            .map_err(|e| ParseError::NotANotification(e.to_string()).record())
+    }
+
+    /// Slot the notification was produced at.
+    pub fn slot(&self) -> u64 {
+        self.params.result.context.slot
+    }
+
+    /// Transaction signature, falling back to the first signature of the
+    /// transaction when the node doesn't send it alongside the result.
+    pub fn signature(&self) -> Option<&str> {
+        let value = &self.params.result.value;
+        if let Some(signature) = &value.signature {
+            return Some(signature);
+        }
+        value.transaction
+            .as_ref()?
+            .transaction.as_ref()?
+            .signatures.as_ref()?
+            .first()
+            .map(String::as_str)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Transaction2 {
     message: Option<Message>,
@@ -42,6 +77,8 @@ pub struct Transaction2 {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Transaction {
+    #[serde(rename = "blockTime")]
+    block_time: Option<i64>,
     version: Option<u64>,
     meta: Option<Meta>,
     transaction: Option<Transaction2>,
@@ -50,6 +87,7 @@ pub struct Transaction {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Message {
     accountKeys: Vec<AccountKey>,
+    #[serde(default)]
     addressTableLookups: Vec<AddressTableLookup>,
     instructions: Vec<Instruction>,
     recentBlockhash: String,
@@ -68,19 +106,37 @@ pub struct AddressTableLookup {
     // Define the fields here if any
 }
 
+/// Top-level or inner instruction in `jsonParsed` encoding: programs the node
+/// knows how to parse carry `parsed`, everything else (Moonshot included)
+/// carries raw `accounts` and base58 `data`.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Instruction {
+    #[serde(default)]
     accounts: Vec<String>,
+    #[serde(default)]
     data: String,
+    #[serde(default, deserialize_with = "lenient")]
+    parsed: Option<Parsed>,
+    program: Option<String>,
     programId: String,
     stackHeight: Option<u64>,
 }
 
+/// Deserializes to `None` instead of failing when the value has another
+/// shape (e.g. spl-memo's `parsed` is a plain string).
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where D: serde::Deserializer<'de>, T: serde::de::DeserializeOwned
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Meta {
     computeUnitsConsumed: u64,
     err: Option<serde_json::Value>,
     fee: u64,
+    #[serde(default)]
     innerInstructions: Vec<InnerInstruction>,
     logMessages: Vec<String>,
     postBalances: Vec<u64>,
@@ -94,15 +150,7 @@ pub struct Meta {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct InnerInstruction {
     index: u64,
-    instructions: Vec<ParsedInstruction>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ParsedInstruction {
-    parsed: Option<Parsed>,
-    program: Option<String>,
-    programId: String,
-    stackHeight: u64,
+    instructions: Vec<Instruction>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -152,11 +200,133 @@ pub struct Status {
     Ok: Option<serde_json::Value>,
 }
 
+/// Why a notification (or one instruction in it) produced no event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The message isn't a `transactionNotification` we understand
+    NotANotification(String),
+    /// A field the parser relies on is absent
+    MissingField(&'static str),
+    /// Instruction data isn't valid base58
+    InvalidEncoding(String),
+    UnknownDiscriminator(String),
+    TruncatedData {
+        needed: usize,
+        remaining: usize,
+    },
+    BadPubkey(String),
+    /// Anything else the IDL decoder rejected (bad UTF-8, enum tag, ...)
+    InvalidData(String),
+}
+
+const PARSE_ERROR_KINDS: [&str; 7] = [
+    "not_a_notification",
+    "missing_field",
+    "invalid_encoding",
+    "unknown_discriminator",
+    "truncated_data",
+    "bad_pubkey",
+    "invalid_data",
+];
+
+static PARSE_ERROR_COUNTS: [AtomicU64; 7] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+
+impl ParseError {
+    fn index(&self) -> usize {
+        match self {
+            Self::NotANotification(_) => 0,
+            Self::MissingField(_) => 1,
+            Self::InvalidEncoding(_) => 2,
+            Self::UnknownDiscriminator(_) => 3,
+            Self::TruncatedData { .. } => 4,
+            Self::BadPubkey(_) => 5,
+            Self::InvalidData(_) => 6,
+        }
+    }
+
+    /// Stable, snake_case name of the variant, used as the counter label.
+    pub fn kind(&self) -> &'static str {
+        PARSE_ERROR_KINDS[self.index()]
+    }
+
+    fn record(self) -> Self {
+        PARSE_ERROR_COUNTS[self.index()].fetch_add(1, Ordering::Relaxed);
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotANotification(reason) => write!(f, "not a transaction notification: {}", reason),
+            Self::MissingField(field) => write!(f, "missing field {}", field),
+            Self::InvalidEncoding(reason) => write!(f, "invalid instruction encoding: {}", reason),
+            Self::UnknownDiscriminator(disc) => write!(f, "unknown discriminator {}", disc),
+            Self::TruncatedData { needed, remaining } =>
+                write!(f, "truncated data: need {} bytes, have {}", needed, remaining),
+            Self::BadPubkey(pubkey) => write!(f, "invalid pubkey {}", pubkey),
+            Self::InvalidData(reason) => write!(f, "invalid data: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<DecodeError> for ParseError {
+    fn from(error: DecodeError) -> Self {
+        match error {
+            DecodeError::UnknownDiscriminator(disc) => Self::UnknownDiscriminator(hex::encode(disc)),
+            DecodeError::Truncated { needed, remaining } => Self::TruncatedData { needed, remaining },
+            DecodeError::BadPubkey(pubkey) => Self::BadPubkey(pubkey),
+            DecodeError::Invalid(reason) => Self::InvalidData(reason),
+        }
+    }
+}
+
+/// Number of parse failures per [`ParseError::kind`] since startup.
+pub fn parse_error_counts() -> Vec<(&'static str, u64)> {
+    PARSE_ERROR_KINDS.iter()
+        .zip(PARSE_ERROR_COUNTS.iter())
+        .map(|(kind, count)| (*kind, count.load(Ordering::Relaxed)))
+        .collect()
+}
+
+/// Transaction-level facts shared by every event decoded from it.
+#[derive(Debug, Clone)]
+pub struct EventContext {
+    pub signature: String,
+    pub slot: u64,
+    /// Not known yet for `processed` notifications
+    pub block_time: Option<i64>,
+    pub fee_payer: Pubkey,
+    pub fee: u64,
+    pub compute_units: u64,
+    pub success: bool,
+    /// Position of the instruction among the transaction's Moonshot
+    /// instructions; `(signature, index)` identifies an event uniquely.
+    pub index: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventEnvelope {
+    pub context: EventContext,
+    pub event: MoonEvent,
+}
+
 #[derive(Debug, Clone)]
 pub enum MoonEvent {
     SellEvent(SellEvent),
     BuyEvent(BuyEvent),
     CreateEvent(CreateEvent),
+    Migration(MigrationEvent),
 }
 
 #[derive(Debug, Clone)]
@@ -167,64 +337,235 @@ pub struct CreateEvent {
     pub sender: Pubkey,
     pub curve_account: Pubkey,
     pub mint: Pubkey,
+    /// Raw supply minted to the curve
+    pub total_supply: u64,
+    pub decimals: u8,
     pub buy_event: Option<BuyEvent>,
 }
 
 impl CreateEvent {
-    pub fn from_hex(
-        hex_data: &str,
-        accounts: Vec<String>,
-        hex_data_buy_dev: &str
-    ) -> anyhow::Result<Self> {
-        let bytes = hex::decode(hex_data)?;
-
-        // Extract data according to the given structure
-        let name_len = u32::from_le_bytes(bytes[8..12].try_into()?) as usize;
-        let name = String::from_utf8(bytes[12..12 + name_len].to_vec())?.to_string();
-
-        let symbol_offset = 12 + name_len + 4; // including the 4 bytes for the length
-        let symbol_len = u32::from_le_bytes(
-            bytes[symbol_offset - 4..symbol_offset].try_into()?
-        ) as usize;
-        let symbol = String::from_utf8(
-            bytes[symbol_offset..symbol_offset + symbol_len].to_vec()
-        )?.to_string();
-
-        let uri_offset = symbol_offset + symbol_len + 4; // including the 4 bytes for the length
-        let uri_len = u32::from_le_bytes(bytes[uri_offset - 4..uri_offset].try_into()?) as usize;
-        let uri = String::from_utf8(bytes[uri_offset..uri_offset + uri_len].to_vec())?.to_string();
-
-        let sender = &accounts[0];
-        let curve_account = &accounts[2];
-        let mint = &accounts[3];
-        if hex_data_buy_dev.is_empty() {
-            return Ok(CreateEvent {
-                name,
-                symbol,
-                uri,
-                sender: Pubkey::from_str(&sender).unwrap(),
-                curve_account: Pubkey::from_str(&curve_account).unwrap(),
-                mint: Pubkey::from_str(&mint).unwrap(),
-                buy_event: None,
-            });
+    /// Fully diluted market cap in SOL at the dev buy's execution price.
+    pub fn market_cap_sol(&self) -> Option<f64> {
+        let price = self.buy_event.as_ref()?.execution?.price;
+        if price <= 0.0 {
+            return None;
         }
-        let buy_event = BuyEvent::from_hex(
-            hex_data_buy_dev,
-            accounts.clone()
-        ).unwrap();
+        Some(price * ((self.total_supply as f64) / (10f64).powi(self.decimals as i32)))
+    }
+}
+
+impl CreateEvent {
+    pub fn from_instruction(
+        create: &DecodedInstruction,
+        dev_buy: Option<&DecodedInstruction>
+    ) -> Result<Self, ParseError> {
+        let buy_event = match dev_buy {
+            Some(dev_buy) => Some(BuyEvent::from_instruction(dev_buy)?),
+            None => None,
+        };
 
         Ok(CreateEvent {
-            name,
-            symbol,
-            uri,
-            sender: Pubkey::from_str(&sender).unwrap(),
-            curve_account: Pubkey::from_str(&curve_account).unwrap(),
-            mint: Pubkey::from_str(&mint).unwrap(),
-            buy_event: Some(buy_event),
+            name: required_str(create, "/mintParams/name")?,
+            symbol: required_str(create, "/mintParams/symbol")?,
+            uri: required_str(create, "/mintParams/uri")?,
+            sender: required_account(create, "sender")?,
+            curve_account: required_account(create, "curveAccount")?,
+            mint: required_account(create, "mint")?,
+            total_supply: required_u64(create, "/mintParams/amount")?,
+            decimals: u8::try_from(required_u64(create, "/mintParams/decimals")?).map_err(|_|
+                ParseError::InvalidData("decimals out of range".to_string())
+            )?,
+            buy_event,
+        })
+    }
+}
+
+/// Pool program a graduated token's liquidity can move to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dex {
+    RaydiumAmm,
+    RaydiumCpmm,
+    MeteoraDamm,
+    MeteoraDammV2,
+}
+
+impl Dex {
+    const ALL: [Dex; 4] = [Dex::RaydiumAmm, Dex::RaydiumCpmm, Dex::MeteoraDamm, Dex::MeteoraDammV2];
+
+    pub fn program_id(self) -> &'static str {
+        match self {
+            Dex::RaydiumAmm => "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
+            Dex::RaydiumCpmm => "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C",
+            Dex::MeteoraDamm => "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB",
+            Dex::MeteoraDammV2 => "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG",
+        }
+    }
+
+    /// The program's pool-initializing instructions, with their accounts
+    /// named as in the program's IDL.
+    fn pool_instructions(self) -> Vec<PoolInstruction> {
+        let anchor = |name: &str| idl::discriminator("global", name).to_vec();
+        match self {
+            // Not an Anchor program: `initialize2` is tagged with its index
+            Dex::RaydiumAmm => vec![PoolInstruction {
+                data_prefix: vec![1],
+                accounts: &[
+                    "tokenProgram", "splAssociatedTokenAccount", "systemProgram", "rent", "amm", "ammAuthority",
+                    "ammOpenOrders", "lpMint", "coinMint", "pcMint", "poolCoinTokenAccount", "poolPcTokenAccount",
+                    "poolWithdrawQueue", "ammTargetOrders", "poolTempLp", "serumProgram", "serumMarket", "userWallet",
+                    "userTokenCoin", "userTokenPc", "userLpTokenAccount",
+                ],
+            }],
+            Dex::RaydiumCpmm => vec![PoolInstruction {
+                data_prefix: anchor("initialize"),
+                accounts: &[
+                    "creator", "ammConfig", "authority", "poolState", "token0Mint", "token1Mint", "lpMint",
+                    "creatorToken0", "creatorToken1", "creatorLpToken", "token0Vault", "token1Vault", "createPoolFee",
+                    "observationState", "tokenProgram", "token0Program", "token1Program", "associatedTokenProgram",
+                    "systemProgram", "rent",
+                ],
+            }],
+            Dex::MeteoraDamm => vec![
+                PoolInstruction {
+                    data_prefix: anchor("initialize_permissionless_constant_product_pool_with_config"),
+                    accounts: &[
+                        "pool", "config", "lpMint", "tokenAMint", "tokenBMint", "aVault", "bVault", "aTokenVault",
+                        "bTokenVault", "aVaultLpMint", "bVaultLpMint", "aVaultLp", "bVaultLp", "payerTokenA",
+                        "payerTokenB", "payerPoolLp", "protocolTokenAFee", "protocolTokenBFee", "payer", "rent",
+                        "mintMetadata", "metadataProgram", "vaultProgram", "tokenProgram", "associatedTokenProgram",
+                        "systemProgram",
+                    ],
+                },
+                PoolInstruction {
+                    data_prefix: anchor("initialize_permissionless_pool"),
+                    accounts: &[
+                        "pool", "lpMint", "tokenAMint", "tokenBMint", "aVault", "bVault", "aVaultLpMint",
+                        "bVaultLpMint", "aVaultLp", "bVaultLp", "payerTokenA", "payerTokenB", "payerPoolLp",
+                        "protocolTokenAFee", "protocolTokenBFee", "payer", "feeOwner", "rent", "mintMetadata",
+                        "metadataProgram", "vaultProgram", "tokenProgram", "associatedTokenProgram", "systemProgram",
+                    ],
+                },
+            ],
+            Dex::MeteoraDammV2 => vec![PoolInstruction {
+                data_prefix: anchor("initialize_pool"),
+                accounts: &[
+                    "creator", "positionNftMint", "positionNftAccount", "payer", "config", "poolAuthority", "pool",
+                    "position", "tokenAMint", "tokenBMint", "tokenAVault", "tokenBVault", "payerTokenA",
+                    "payerTokenB", "tokenAProgram", "tokenBProgram", "token2022Program", "systemProgram",
+                    "eventAuthority", "program",
+                ],
+            }],
+        }
+    }
+
+    /// IDL name of the pool account in [`Dex::pool_instructions`].
+    fn pool_account(self) -> &'static str {
+        match self {
+            Dex::RaydiumAmm => "amm",
+            Dex::RaydiumCpmm => "poolState",
+            Dex::MeteoraDamm | Dex::MeteoraDammV2 => "pool",
+        }
+    }
+
+    fn from_program_id(program_id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|dex| dex.program_id() == program_id)
+    }
+}
+
+/// A pool-initializing instruction of a [`Dex`]: how its data starts and
+/// the IDL names of its accounts, in order.
+struct PoolInstruction {
+    data_prefix: Vec<u8>,
+    accounts: &'static [&'static str],
+}
+
+impl fmt::Display for Dex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Dex::RaydiumAmm => "Raydium AMM",
+            Dex::RaydiumCpmm => "Raydium CPMM",
+            Dex::MeteoraDamm => "Meteora DAMM",
+            Dex::MeteoraDammV2 => "Meteora DAMM v2",
+        })
+    }
+}
+
+/// A completed curve handing its funds to the migration authority
+/// (`migrateFunds`).
+#[derive(Debug, Clone)]
+pub struct MigrationEvent {
+    pub mint: Pubkey,
+    pub curve_account: Pubkey,
+    pub migration_authority: Pubkey,
+    /// Destination pool, when it is created in the same transaction
+    pub pool: Option<(Dex, Pubkey)>,
+    /// Lamports that left the curve account
+    pub sol_lamports: Option<u64>,
+    /// Raw tokens that left the curve's token account
+    pub token_amount: Option<u64>,
+}
+
+impl MigrationEvent {
+    pub fn from_instruction(
+        migrate: &DecodedInstruction,
+        message: &Message,
+        meta: &Meta
+    ) -> Result<Self, ParseError> {
+        let mint = required_account(migrate, "mint")?;
+        let curve_account = required_account(migrate, "curveAccount")?;
+        let curve_token_account = required_account(migrate, "curveTokenAccount")?;
+        let index_of = |account: &Pubkey| {
+            let account = account.to_string();
+            message.accountKeys.iter().position(|key| key.pubkey == account)
+        };
+
+        let sol_lamports = index_of(&curve_account).and_then(|index| {
+            let pre = *meta.preBalances.get(index)?;
+            let post = *meta.postBalances.get(index)?;
+            Some(pre.saturating_sub(post))
+        });
+        let token_amount = index_of(&curve_token_account).map(|index| {
+            let balance = |balances: &[TokenBalance]| -> u64 {
+                balances
+                    .iter()
+                    .find(|balance| balance.accountIndex == (index as u64))
+                    .and_then(|balance| balance.uiTokenAmount.amount.parse().ok())
+                    .unwrap_or_default()
+            };
+            balance(&meta.preTokenBalances).saturating_sub(balance(&meta.postTokenBalances))
+        });
+
+        Ok(MigrationEvent {
+            mint,
+            curve_account,
+            migration_authority: required_account(migrate, "migrationAuthority")?,
+            pool: destination_pool(message, meta, &mint),
+            sol_lamports,
+            token_amount,
         })
     }
 }
 
+/// First pool-initializing instruction of a known DEX that involves `mint`,
+/// taken as the pool creation the migrated liquidity goes to.
+fn destination_pool(message: &Message, meta: &Meta, mint: &Pubkey) -> Option<(Dex, Pubkey)> {
+    let mint = mint.to_string();
+    message.instructions
+        .iter()
+        .chain(meta.innerInstructions.iter().flat_map(|inner| inner.instructions.iter()))
+        .filter(|instruction| instruction.accounts.contains(&mint))
+        .find_map(|instruction| {
+            let dex = Dex::from_program_id(&instruction.programId)?;
+            let data = bs58::decode(&instruction.data).into_vec().ok()?;
+            let pool_instruction = dex
+                .pool_instructions()
+                .into_iter()
+                .find(|pool_instruction| data.starts_with(&pool_instruction.data_prefix))?;
+            let index = pool_instruction.accounts.iter().position(|name| *name == dex.pool_account())?;
+            Some((dex, Pubkey::from_str(instruction.accounts.get(index)?).ok()?))
+        })
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SellEvent {
     pub amount: u64,
@@ -233,25 +574,20 @@ pub struct SellEvent {
     pub sender: Pubkey,
     pub curve_account: Pubkey,
     pub mint: Pubkey,
+    /// What actually executed, from the transaction's balance changes
+    pub execution: Option<TradeExecution>,
 }
 
 impl SellEvent {
-    pub fn from_hex(hex_data: &str, accounts: Vec<String>) -> anyhow::Result<Self> {
-        let bytes = hex::decode(hex_data).expect("Decoding failed");
-        let amount = u64::from_le_bytes(bytes[8..16].try_into()?);
-        let collateral_amount = u64::from_le_bytes(bytes[16..24].try_into()?);
-        let slippage_bps = u64::from_le_bytes(bytes[24..32].try_into()?);
-        let sender = &accounts[0];
-        let curve_account = &accounts[3];
-        let mint = &accounts[7];
-
+    pub fn from_instruction(sell: &DecodedInstruction) -> Result<Self, ParseError> {
         Ok(SellEvent {
-            amount,
-            collateral_amount,
-            slippage_bps: slippage_bps,
-            sender: Pubkey::from_str(&sender).unwrap(),
-            curve_account: Pubkey::from_str(&curve_account).unwrap(),
-            mint: Pubkey::from_str(&mint).unwrap(),
+            amount: required_u64(sell, "/data/tokenAmount")?,
+            collateral_amount: required_u64(sell, "/data/collateralAmount")?,
+            slippage_bps: required_u64(sell, "/data/slippageBps")?,
+            sender: required_account(sell, "sender")?,
+            curve_account: required_account(sell, "curveAccount")?,
+            mint: required_account(sell, "mint")?,
+            execution: None,
         })
     }
 }
@@ -264,116 +600,627 @@ pub struct BuyEvent {
     pub sender: Pubkey,
     pub curve_account: Pubkey,
     pub mint: Pubkey,
+    /// What actually executed, from the transaction's balance changes
+    pub execution: Option<TradeExecution>,
 }
 
 impl BuyEvent {
-    pub fn from_hex(hex_data: &str, accounts: Vec<String>) -> anyhow::Result<Self> {
-        let bytes = hex::decode(hex_data).expect("Decoding failed");
-        let amount = u64::from_le_bytes(bytes[8..16].try_into()?);
-        let collateral_amount = u64::from_le_bytes(bytes[16..24].try_into()?);
-        let slippage_bps = u64::from_le_bytes(bytes[24..32].try_into()?);
-        let sender = &accounts[0];
-        let curve_account = &accounts[3];
-        let mint = &accounts[7];
-
+    pub fn from_instruction(buy: &DecodedInstruction) -> Result<Self, ParseError> {
         Ok(BuyEvent {
-            amount,
-            collateral_amount,
-            slippage_bps: slippage_bps,
-            sender: Pubkey::from_str(&sender).unwrap(),
-            curve_account: Pubkey::from_str(&curve_account).unwrap(),
-            mint: Pubkey::from_str(&mint).unwrap(),
+            amount: required_u64(buy, "/data/tokenAmount")?,
+            collateral_amount: required_u64(buy, "/data/collateralAmount")?,
+            slippage_bps: required_u64(buy, "/data/slippageBps")?,
+            sender: required_account(buy, "sender")?,
+            curve_account: required_account(buy, "curveAccount")?,
+            mint: required_account(buy, "mint")?,
+            execution: None,
         })
     }
 }
-pub async fn parse_pump_event(message_obj: serde_json::Value) -> Option<MoonEvent> {
-    let message_obj: TransactionNotification = serde_json
-        ::from_value::<TransactionNotification>(message_obj.clone())
-        .unwrap();
 
-    let data: String = message_obj.params.result.value.transaction
-        .clone()
-        .unwrap()
-        .transaction.unwrap()
-        .message.unwrap()
-        .instructions[1].data.clone();
+/// A trade as settled on-chain, derived from pre/post balances of the trader.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TradeExecution {
+    /// Lamports the trader spent (buy) or received (sell), excluding the
+    /// transaction fee and rent for accounts created in the transaction
+    pub sol_lamports: u64,
+    /// Raw token amount received (buy) or sent (sell)
+    pub token_amount: u64,
+    /// Raw token balance the trader holds after the transaction
+    pub token_balance_after: u64,
+    pub decimals: u8,
+    /// Effective SOL paid or received per whole token
+    pub price: f64,
+}
 
-    let decoded_bytes = bs58::decode(data.clone()).into_vec().unwrap();
-    let mut decoded_bytes_dev_buy: Vec<u8> = Vec::new();
+impl TradeExecution {
+    pub fn sol(&self) -> f64 {
+        (self.sol_lamports as f64) / 1_000_000_000.0
+    }
 
-    if
-        message_obj.params.result.value.transaction
-            .clone()
-            .unwrap()
-            .transaction.unwrap()
-            .message.unwrap()
-            .instructions.len() > 2
-    {
-        let data_dev_buy: String = message_obj.params.result.value.transaction
-            .clone()
-            .unwrap()
-            .transaction.unwrap()
-            .message.unwrap()
-            .instructions[2].data.clone();
+    pub fn tokens(&self) -> f64 {
+        (self.token_amount as f64) / (10f64).powi(self.decimals as i32)
+    }
+
+    /// Nets the trader's balance changes in `meta`. Only meaningful when the
+    /// trader has a single trade on `mint` in the transaction: several trades
+    /// would all net into one change.
+    fn from_balances(
+        message: &Message,
+        meta: &Meta,
+        trader: &Pubkey,
+        mint: &Pubkey,
+        is_buy: bool
+    ) -> Option<Self> {
+        let trader = trader.to_string();
+        let mint = mint.to_string();
+
+        let index = message.accountKeys.iter().position(|key| key.pubkey == trader)?;
+        let mut lamports =
+            (*meta.postBalances.get(index)? as i128) - (*meta.preBalances.get(index)? as i128);
+        if index == 0 {
+            lamports += meta.fee as i128;
+        }
+        // Rent for token accounts opened on the way isn't part of the trade
+        let rent: u64 = message.instructions
+            .iter()
+            .chain(meta.innerInstructions.iter().flat_map(|inner| inner.instructions.iter()))
+            .filter_map(|instruction| instruction.parsed.as_ref())
+            .filter(|parsed| parsed.type_ == "createAccount" || parsed.type_ == "createAccountWithSeed")
+            .filter(|parsed| parsed.info.source.as_deref() == Some(trader.as_str()))
+            .filter_map(|parsed| parsed.info.lamports)
+            .sum();
+        lamports += rent as i128;
+
+        let token_total = |balances: &[TokenBalance]| -> (i128, Option<u8>) {
+            balances
+                .iter()
+                .filter(|balance| balance.owner == trader && balance.mint == mint)
+                .fold((0, None), |(total, decimals), balance| {
+                    let amount = balance.uiTokenAmount.amount.parse::<u64>().unwrap_or_default();
+                    (total + (amount as i128), decimals.or(Some(balance.uiTokenAmount.decimals as u8)))
+                })
+        };
+        let (pre_tokens, pre_decimals) = token_total(&meta.preTokenBalances);
+        let (post_tokens, post_decimals) = token_total(&meta.postTokenBalances);
+        let decimals = post_decimals.or(pre_decimals)?;
 
-        decoded_bytes_dev_buy = bs58::decode(data_dev_buy.clone()).into_vec().unwrap();
+        let (sol_lamports, token_amount) = if is_buy {
+            (-lamports, post_tokens - pre_tokens)
+        } else {
+            (lamports, pre_tokens - post_tokens)
+        };
+        let sol_lamports = u64::try_from(sol_lamports.max(0)).ok()?;
+        let token_amount = u64::try_from(token_amount.max(0)).ok()?;
+
+        let token_balance_after = u64::try_from(post_tokens).ok()?;
+
+        let mut execution = TradeExecution {
+            sol_lamports,
+            token_amount,
+            token_balance_after,
+            decimals,
+            price: 0.0,
+        };
+        if token_amount > 0 {
+            execution.price = execution.sol() / execution.tokens();
+        }
+        Some(execution)
     }
+}
 
-    let hex_str: String = decoded_bytes
-        .iter()
-        .map(|b| format!("{:02x}", b))
-        .collect::<String>();
-
-    let disc_hex = &hex_str[0..16];
-    let accounts: Vec<String> = message_obj.params.result.value.transaction
-        .clone()
-        .unwrap()
-        .transaction.unwrap()
-        .message.unwrap()
-        .instructions[1].accounts.clone();
-
-    match disc_hex {
-        "33e685a4017f83ad" => {
-            let sell_event: SellEvent = match
-                SellEvent::from_hex(&hex::encode(decoded_bytes.as_slice()), accounts.clone())
-            {
-                Ok(buy_event) => buy_event,
-                Err(_) => {
-                    return None;
+impl MoonEvent {
+    /// Executed amounts of a buy or sell, if known.
+    pub fn execution(&self) -> Option<TradeExecution> {
+        match self {
+            MoonEvent::BuyEvent(buy) => buy.execution,
+            MoonEvent::SellEvent(sell) => sell.execution,
+            MoonEvent::CreateEvent(_) | MoonEvent::Migration(_) => None,
+        }
+    }
+
+    /// Trader and mint of a buy or sell.
+    fn trade_key(&self) -> Option<(Pubkey, Pubkey)> {
+        match self {
+            MoonEvent::BuyEvent(buy) => Some((buy.sender, buy.mint)),
+            MoonEvent::SellEvent(sell) => Some((sell.sender, sell.mint)),
+            MoonEvent::CreateEvent(_) | MoonEvent::Migration(_) => None,
+        }
+    }
+
+    /// Attaches the execution derived from balance changes, unless the
+    /// trader made other trades on the same mint in the transaction
+    /// (`trade_counts`); those keep only their instruction amounts.
+    fn attach_execution(&mut self, message: &Message, meta: &Meta, trade_counts: &HashMap<(Pubkey, Pubkey), usize>) {
+        let single = |trader: &Pubkey, mint: &Pubkey| trade_counts.get(&(*trader, *mint)) == Some(&1);
+        match self {
+            MoonEvent::BuyEvent(buy) if single(&buy.sender, &buy.mint) => {
+                buy.execution = TradeExecution::from_balances(message, meta, &buy.sender, &buy.mint, true);
+            }
+            MoonEvent::SellEvent(sell) if single(&sell.sender, &sell.mint) => {
+                sell.execution = TradeExecution::from_balances(
+                    message,
+                    meta,
+                    &sell.sender,
+                    &sell.mint,
+                    false
+                );
+            }
+            MoonEvent::CreateEvent(create) => {
+                if let Some(buy) = create.buy_event.as_mut().filter(|buy| single(&buy.sender, &buy.mint)) {
+                    buy.execution = TradeExecution::from_balances(
+                        message,
+                        meta,
+                        &buy.sender,
+                        &buy.mint,
+                        true
+                    );
                 }
-            };
+            }
+            _ => {}
+        }
+    }
+}
+
+fn required_u64(instruction: &DecodedInstruction, pointer: &'static str) -> Result<u64, ParseError> {
+    instruction.arg_u64(pointer).ok_or(ParseError::MissingField(pointer))
+}
 
-            Some(MoonEvent::SellEvent(sell_event))
+fn required_str(
+    instruction: &DecodedInstruction,
+    pointer: &'static str
+) -> Result<String, ParseError> {
+    instruction.arg_str(pointer).map(str::to_string).ok_or(ParseError::MissingField(pointer))
+}
+
+fn required_account(
+    instruction: &DecodedInstruction,
+    name: &'static str
+) -> Result<Pubkey, ParseError> {
+    instruction.account(name).ok_or(ParseError::MissingField(name))
+}
+
+fn decode_instruction(instruction: &Instruction) -> Result<DecodedInstruction, ParseError> {
+    let data = bs58
+        ::decode(&instruction.data)
+        .into_vec()
+        .map_err(|e| ParseError::InvalidEncoding(e.to_string()))?;
+    Ok(idl::moonshot().decode_instruction(&data, &instruction.accounts)?)
+}
+
+/// All Moonshot instructions of a transaction in execution order: each
+/// top-level instruction followed by the inner instructions it invoked, so
+/// trades routed through aggregators via CPI are included.
+fn moonshot_instructions<'a>(
+    message: &'a Message,
+    meta: &'a Meta,
+    program_id: &str
+) -> Vec<&'a Instruction> {
+    let mut instructions = Vec::new();
+    for (index, instruction) in message.instructions.iter().enumerate() {
+        instructions.push(instruction);
+        for inner in meta.innerInstructions.iter().filter(|inner| inner.index == (index as u64)) {
+            instructions.extend(inner.instructions.iter());
         }
-        "66063d1201daebea" => {
-            let buy_event: BuyEvent = match
-                BuyEvent::from_hex(&hex::encode(decoded_bytes.as_slice()), accounts.clone())
-            {
-                Ok(buy_event) => buy_event,
-                Err(_) => {
-                    return None;
+    }
+    instructions.retain(|instruction| instruction.programId == program_id);
+    instructions
+}
+
+/// Decodes every Moonshot instruction in a notification into events. Instructions that fail to decode are counted and skipped; the
+/// first such error is returned only if nothing else could be decoded.
+pub fn parse_pump_event(
+    message_obj: TransactionNotification,
+    program_id: &Pubkey
+) -> Result<Vec<EventEnvelope>, ParseError> {
+    decode_events(message_obj, program_id, true)
+}
+
+/// Like [`parse_pump_event`], for transactions that may not involve Moonshot at all (e.g. a wallet's history): failures are neither
+/// counted nor logged and just yield no events.
+pub fn scan_pump_events(message_obj: TransactionNotification, program_id: &Pubkey) -> Vec<EventEnvelope> {
+    decode_events(message_obj, program_id, false).unwrap_or_default()
+}
+
+fn decode_events(
+    message_obj: TransactionNotification,
+    program_id: &Pubkey,
+    report: bool
+) -> Result<Vec<EventEnvelope>, ParseError> {
+    let fail = |error: ParseError| if report { error.record() } else { error };
+    let signature = message_obj
+        .signature()
+        .map(str::to_string)
+        .ok_or_else(|| fail(ParseError::MissingField("signature")))?;
+    let value = message_obj.params.result.value;
+    let transaction = value.transaction
+        .ok_or_else(|| fail(ParseError::MissingField("transaction")))?;
+    let meta = transaction.meta.ok_or_else(|| fail(ParseError::MissingField("meta")))?;
+    let message = transaction.transaction
+        .and_then(|transaction| transaction.message)
+        .ok_or_else(|| fail(ParseError::MissingField("message")))?;
+    let program_id = program_id.to_string();
+    let instructions = moonshot_instructions(&message, &meta, &program_id);
+    if instructions.is_empty() {
+        return Ok(Vec::new());
+    }
+    let fee_payer = message.accountKeys
+        .first()
+        .ok_or_else(|| fail(ParseError::MissingField("accountKeys")))?;
+    let fee_payer = Pubkey::from_str(&fee_payer.pubkey).map_err(|_|
+        fail(ParseError::BadPubkey(fee_payer.pubkey.clone()))
+    )?;
+
+    let context = EventContext {
+        signature,
+        slot: value.slot,
+        block_time: value.block_time.or(transaction.block_time),
+        fee_payer,
+        fee: meta.fee,
+        compute_units: meta.computeUnitsConsumed,
+        success: meta.err.is_none(),
+        index: 0,
+    };
+
+    let mut first_error = None;
+    let mut decoded = Vec::new();
+    for (index, instruction) in instructions.into_iter().enumerate() {
+        match decode_instruction(instruction) {
+            Ok(instruction) => decoded.push((index, instruction)),
+            Err(e) => {
+                first_error.get_or_insert(fail(e));
+            }
+        }
+    }
+
+    let mut events = Vec::new();
+    for (position, (index, instruction)) in decoded.iter().enumerate() {
+        let event = match instruction.name.as_str() {
+            "sell" => SellEvent::from_instruction(instruction).map(MoonEvent::SellEvent),
+            "buy" => BuyEvent::from_instruction(instruction).map(MoonEvent::BuyEvent),
+            "tokenMint" => {
+                // The dev buy is the first buy of the new mint by its creator
+                // later in the same transaction; it is also reported as a BuyEvent.
+                let dev_buy = decoded[position + 1..]
+                    .iter()
+                    .map(|(_, other)| other)
+                    .find(|other| {
+                        other.name == "buy" &&
+                            other.account("mint") == instruction.account("mint") &&
+                            other.account("sender") == instruction.account("sender")
+                    });
+                CreateEvent::from_instruction(instruction, dev_buy).map(MoonEvent::CreateEvent)
+            }
+            "migrateFunds" =>
+                MigrationEvent::from_instruction(instruction, &message, &meta).map(MoonEvent::Migration),
+            name => {
+                if report {
+                    eprintln!("Skipping unhandled {} instruction in {}", name, context.signature);
                 }
-            };
-            Some(MoonEvent::BuyEvent(buy_event))
+                continue;
+            }
+        };
+        match event {
+            Ok(event) => {
+                events.push(EventEnvelope {
+                    context: EventContext { index: *index, ..context.clone() },
+                    event,
+                });
+            }
+            Err(e) => {
+                if report {
+                    eprintln!("Failed to build {} event in {}: {}", instruction.name, context.signature, e);
+                }
+                first_error.get_or_insert(fail(e));
+            }
         }
-        "032ca4b87b0df5b3" => {
-            let create_event: CreateEvent = match
-                CreateEvent::from_hex(
-                    &hex::encode(decoded_bytes.as_slice()),
-                    accounts.clone(),
-                    &hex::encode(decoded_bytes_dev_buy.as_slice())
-                )
-            {
-                Ok(create_event) => { create_event }
-                Err(_) => {
-                    return None;
+    }
+
+    let mut trade_counts = HashMap::new();
+    for envelope in &events {
+        if let Some(key) = envelope.event.trade_key() {
+            *trade_counts.entry(key).or_insert(0) += 1;
+        }
+    }
+    for envelope in events.iter_mut() {
+        envelope.event.attach_execution(&message, &meta, &trade_counts);
+    }
+
+    match first_error {
+        Some(e) if events.is_empty() => Err(e),
+        _ => Ok(events),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{ json, Value as Json };
+
+    use super::*;
+    use crate::trades::TradeAggregator;
+
+    const TRADER: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+    const MINT: &str = "3DTdh4oRr8bHyfh8RbVfcLXa8UUPpsQCvZUqEfpWmoon";
+    const SLOT: u64 = 280_000_000;
+    const FEE: u64 = 5000;
+    const LAMPORTS: u64 = 1_000_000_000;
+
+    /// Anchor `buy`/`sell` instruction data: discriminator, then `TradeParams`.
+    fn trade_data(name: &str, token_amount: u64, collateral_amount: u64) -> String {
+        let mut data = idl::discriminator("global", name).to_vec();
+        data.extend_from_slice(&token_amount.to_le_bytes());
+        data.extend_from_slice(&collateral_amount.to_le_bytes());
+        data.push(0);
+        data.extend_from_slice(&100u64.to_le_bytes());
+        bs58::encode(data).into_string()
+    }
+
+    fn token_balance(amount: u64) -> Json {
+        json!({
+            "accountIndex": 2,
+            "mint": MINT,
+            "owner": TRADER,
+            "programId": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+            "uiTokenAmount": {
+                "amount": amount.to_string(),
+                "decimals": 9,
+                "uiAmount": (amount as f64) / 1e9,
+                "uiAmountString": ((amount as f64) / 1e9).to_string()
+            }
+        })
+    }
+
+    /// A `transactionNotification` in `jsonParsed` encoding for one trader
+    /// running `trades` (name, tokens, lamports) on one mint, with the
+    /// trader's SOL and token balances moving by `lamports_delta` (fee
+    /// excluded) and `tokens_before` → `tokens_after`.
+    fn notification(trades: &[(&str, u64, u64)], lamports_delta: i64, tokens_before: u64, tokens_after: u64) -> Json {
+        let keys = [
+            TRADER,
+            "Cb8Fnhp95f9dLxB3sYkNCbN3Mjxuc3v2uQZ7uVeqvNGB",
+            "9uVTbZrQb3gEJQHeRqz9bViXQXjr6qzaR8CuSyiexdRb",
+            "6FqfhB3bcLzBJjRfYfeZvP7DSShx3ycHMbQaiW1TNfd2",
+            "2oMF9qQrD3Y3eJnuFwKV5TfAZVQEbJyWcbYzqKKqT2mW",
+            "3udvfL24waJcLhskRAsStNMoNUvtyXdxrWQz4hgi953N",
+            "5K5RtTWzzLp4P8Npi84ocf7F1vBsAu29N1irG4iiUnzt",
+            MINT,
+            "36Eru7v11oU5Pfrojyn5oY3nETA1a1iqsw2WUu6afkM9",
+            "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+            "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL",
+            "11111111111111111111111111111111",
+            MOONSHOT_PROGRAM_ID,
+        ];
+        let account_keys: Vec<Json> = keys
+            .iter()
+            .enumerate()
+            .map(|(index, key)| json!({
+                "pubkey": key,
+                "signer": index < 2,
+                "source": "transaction",
+                "writable": index < 7
+            }))
+            .collect();
+        let instructions: Vec<Json> = trades
+            .iter()
+            .map(|(name, tokens, lamports)| json!({
+                "accounts": keys[..12],
+                "data": trade_data(name, *tokens, *lamports),
+                "programId": MOONSHOT_PROGRAM_ID,
+                "stackHeight": null
+            }))
+            .collect();
+        let pre_balances = vec![50 * LAMPORTS; keys.len()];
+        let mut post_balances = pre_balances.clone();
+        post_balances[0] = (((50 * LAMPORTS) as i64) + lamports_delta - (FEE as i64)) as u64;
+
+        json!({
+            "jsonrpc": "2.0",
+            "method": "transactionNotification",
+            "params": {
+                "subscription": 1,
+                "result": {
+                    "context": { "slot": SLOT },
+                    "value": {
+                        "slot": SLOT,
+                        "signature": "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi",
+                        "blockTime": 1_720_000_000,
+                        "transaction": {
+                            "blockTime": 1_720_000_000,
+                            "version": 0,
+                            "meta": {
+                                "computeUnitsConsumed": 120_000,
+                                "err": null,
+                                "fee": FEE,
+                                "innerInstructions": [],
+                                "logMessages": [],
+                                "preBalances": pre_balances,
+                                "postBalances": post_balances,
+                                "preTokenBalances": [token_balance(tokens_before)],
+                                "postTokenBalances": [token_balance(tokens_after)],
+                                "rewards": null,
+                                "status": { "Ok": null }
+                            },
+                            "transaction": {
+                                "signatures": ["4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi"],
+                                "message": {
+                                    "accountKeys": account_keys,
+                                    "instructions": instructions,
+                                    "recentBlockhash": "EkSnNWid2cvwEVnVx9aBqawnmiCNiDgp3gUdkDPTKN1N"
+                                }
+                            }
+                        }
+                    }
                 }
-            };
-            Some(MoonEvent::CreateEvent(create_event))
+            }
+        })
+    }
+
+    fn parse(notification: Json) -> Vec<EventEnvelope> {
+        let notification = TransactionNotification::from_value(notification).unwrap();
+        let program_id = Pubkey::from_str(MOONSHOT_PROGRAM_ID).unwrap();
+        parse_pump_event(notification, &program_id).unwrap()
+    }
+
+    #[test]
+    fn single_trade_gets_execution() {
+        let events = parse(notification(&[("buy", 1_000 * LAMPORTS, LAMPORTS)], -(LAMPORTS as i64), 0, 990 * LAMPORTS));
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].context.block_time, Some(1_720_000_000));
+        let execution = events[0].event.execution().unwrap();
+        assert_eq!(execution.sol_lamports, LAMPORTS);
+        assert_eq!(execution.token_amount, 990 * LAMPORTS);
+        assert_eq!(execution.token_balance_after, 990 * LAMPORTS);
+    }
+
+    /// A round trip in one transaction nets to a small SOL loss and no token
+    /// change; neither trade may be reported as that net change.
+    #[test]
+    fn buy_and_sell_in_one_transaction_keep_their_own_amounts() {
+        let trades = [("buy", 1_000 * LAMPORTS, LAMPORTS), ("sell", 1_000 * LAMPORTS, 980_000_000)];
+        let events = parse(notification(&trades, -20_000_000, 0, 0));
+
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|envelope| envelope.event.execution().is_none()));
+
+        let aggregator = TradeAggregator::new();
+        for envelope in &events {
+            aggregator.record(envelope);
         }
-        _ => {
-            println!("Unknown Event {:?}", data);
-            None
+        let stats = aggregator.get(&Pubkey::from_str(MINT).unwrap()).unwrap();
+        assert_eq!((stats.buys, stats.sells), (1, 1));
+        assert_eq!(stats.buy_lamports, LAMPORTS);
+        assert_eq!(stats.sell_lamports, 980_000_000);
+        let position = stats.position(&Pubkey::from_str(TRADER).unwrap());
+        assert_eq!(position.tokens_bought, 1_000 * LAMPORTS);
+        assert_eq!(position.tokens_sold, 1_000 * LAMPORTS);
+    }
+
+    /// Two buys of the same mint would otherwise each be credited with both.
+    #[test]
+    fn two_buys_are_not_double_counted() {
+        let trades = [("buy", 500 * LAMPORTS, LAMPORTS / 2), ("buy", 500 * LAMPORTS, LAMPORTS / 2)];
+        let events = parse(notification(&trades, -(LAMPORTS as i64), 0, 1_000 * LAMPORTS));
+
+        let aggregator = TradeAggregator::new();
+        for envelope in &events {
+            aggregator.record(envelope);
         }
+        let stats = aggregator.get(&Pubkey::from_str(MINT).unwrap()).unwrap();
+        assert_eq!(stats.buys, 2);
+        assert_eq!(stats.buy_lamports, LAMPORTS);
+        assert_eq!(stats.position(&Pubkey::from_str(TRADER).unwrap()).tokens_bought, 1_000 * LAMPORTS);
+    }
+
+    /// Wallet history scans see undecodable and unrelated transactions alike
+    /// as carrying no events.
+    #[test]
+    fn scanning_ignores_undecodable_and_unrelated_transactions() {
+        let program_id = Pubkey::from_str(MOONSHOT_PROGRAM_ID).unwrap();
+        let undecodable = notification(&[("unknown", LAMPORTS, LAMPORTS)], 0, 0, 0);
+        let decoded = parse_pump_event(TransactionNotification::from_value(undecodable.clone()).unwrap(), &program_id);
+        assert!(matches!(decoded, Err(ParseError::UnknownDiscriminator(_))));
+        let scanned = scan_pump_events(serde_json::from_value(undecodable).unwrap(), &program_id);
+        assert!(scanned.is_empty());
+
+        let mut unrelated = notification(&[("buy", LAMPORTS, LAMPORTS)], 0, 0, 0);
+        unrelated["params"]["result"]["value"]["transaction"]["transaction"]["message"]["instructions"][0]["programId"] =
+            json!("11111111111111111111111111111111");
+        assert!(scan_pump_events(serde_json::from_value(unrelated).unwrap(), &program_id).is_empty());
+    }
+
+    fn token_mint_data(name: &str, symbol: &str, uri: &str, decimals: u8, amount: u64) -> String {
+        let mut data = idl::discriminator("global", "token_mint").to_vec();
+        for text in [name, symbol, uri] {
+            data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            data.extend_from_slice(text.as_bytes());
+        }
+        data.push(decimals);
+        data.push(0);
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&[1, 0]);
+        bs58::encode(data).into_string()
+    }
+
+    #[test]
+    fn token_mint_with_dev_buy() {
+        let mut notification = notification(&[("buy", 1_000 * LAMPORTS, LAMPORTS)], -(LAMPORTS as i64), 0, 1_000 * LAMPORTS);
+        let message = &mut notification["params"]["result"]["value"]["transaction"]["transaction"]["message"];
+        let keys: Vec<Json> = message["accountKeys"].as_array().unwrap().iter().map(|key| key["pubkey"].clone()).collect();
+        let accounts = [&keys[0], &keys[1], &keys[3], &keys[7], &keys[8], &keys[4], &keys[8], &keys[9], &keys[10], &keys[11], &keys[11]];
+        let token_mint = json!({
+            "accounts": accounts,
+            "data": token_mint_data("Moon Token", "MOON", "https://moon.example/meta.json", 9, 1_000_000_000 * LAMPORTS),
+            "programId": MOONSHOT_PROGRAM_ID,
+            "stackHeight": null
+        });
+        message["instructions"].as_array_mut().unwrap().insert(0, token_mint);
+
+        let events = parse(notification);
+        assert_eq!(events.len(), 2);
+        let MoonEvent::CreateEvent(create) = &events[0].event else {
+            panic!("expected a create event, got {:?}", events[0].event);
+        };
+        assert_eq!((create.name.as_str(), create.symbol.as_str()), ("Moon Token", "MOON"));
+        assert_eq!(create.uri, "https://moon.example/meta.json");
+        assert_eq!(create.mint, Pubkey::from_str(MINT).unwrap());
+        assert_eq!(create.sender, Pubkey::from_str(TRADER).unwrap());
+        assert_eq!((create.total_supply, create.decimals), (1_000_000_000 * LAMPORTS, 9));
+        let dev_buy = create.buy_event.as_ref().unwrap();
+        assert_eq!(dev_buy.amount, 1_000 * LAMPORTS);
+        assert!(matches!(events[1].event, MoonEvent::BuyEvent(_)));
+    }
+
+    /// A migration whose liquidity goes to a new DAMM v2 pool; a swap on
+    /// another DEX involving the mint comes first and must not be taken as
+    /// the pool creation.
+    #[test]
+    fn migrate_funds_with_pool_creation() {
+        let mut notification = notification(&[], 0, 300_000_000 * LAMPORTS, 0);
+        let value = &mut notification["params"]["result"]["value"]["transaction"];
+        value["meta"]["postBalances"][3] = json!(10 * LAMPORTS);
+        let keys: Vec<Json> = value["transaction"]["message"]["accountKeys"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|key| key["pubkey"].clone())
+            .collect();
+        let pool = Pubkey::new_unique();
+        let mut pool_accounts: Vec<Json> = (0..20).map(|_| json!(Pubkey::new_unique().to_string())).collect();
+        pool_accounts[6] = json!(pool.to_string());
+        pool_accounts[8] = json!(MINT);
+        let mut swap_accounts = pool_accounts.clone();
+        swap_accounts[3] = json!(Pubkey::new_unique().to_string());
+        value["transaction"]["message"]["instructions"] = json!([
+            {
+                "accounts": [&keys[1], &keys[0], &keys[3], &keys[2], &keys[4], &keys[7], &keys[5], &keys[6], &keys[8], &keys[11], &keys[9], &keys[10]],
+                "data": bs58::encode(idl::discriminator("global", "migrate_funds")).into_string(),
+                "programId": MOONSHOT_PROGRAM_ID,
+                "stackHeight": null
+            },
+            {
+                "accounts": swap_accounts,
+                "data": bs58::encode(idl::discriminator("global", "swap_base_input")).into_string(),
+                "programId": Dex::RaydiumCpmm.program_id(),
+                "stackHeight": null
+            },
+            {
+                "accounts": pool_accounts,
+                "data": bs58::encode(idl::discriminator("global", "initialize_pool")).into_string(),
+                "programId": Dex::MeteoraDammV2.program_id(),
+                "stackHeight": null
+            }
+        ]);
+
+        let events = parse(notification);
+        assert_eq!(events.len(), 1);
+        let MoonEvent::Migration(migration) = &events[0].event else {
+            panic!("expected a migration, got {:?}", events[0].event);
+        };
+        assert_eq!(migration.mint, Pubkey::from_str(MINT).unwrap());
+        assert_eq!(migration.migration_authority, Pubkey::from_str(TRADER).unwrap());
+        assert_eq!(migration.pool, Some((Dex::MeteoraDammV2, pool)));
+        assert_eq!(migration.sol_lamports, Some(40 * LAMPORTS));
+        assert_eq!(migration.token_amount, Some(300_000_000 * LAMPORTS));
     }
 }
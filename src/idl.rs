@@ -0,0 +1,602 @@
+use std::{ collections::HashMap, fmt, str::FromStr, sync::OnceLock };
+
+use serde::Deserialize;
+use serde_json::{ json, Map, Value };
+use solana_sdk::{ hash::hash, pubkey::Pubkey };
+
+/// Anchor IDL of the Moonshot program, shipped with the crate.
+const MOONSHOT_IDL: &str = include_str!("../idl/moonshot.json");
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Idl {
+    pub version: String,
+    pub name: String,
+    pub instructions: Vec<IdlInstruction>,
+    #[serde(default)]
+    pub accounts: Vec<IdlTypeDef>,
+    #[serde(default)]
+    pub types: Vec<IdlTypeDef>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    pub accounts: Vec<IdlAccount>,
+    pub args: Vec<IdlField>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlAccount {
+    pub name: String,
+    #[serde(default)]
+    pub is_mut: bool,
+    #[serde(default)]
+    pub is_signer: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlType {
+    Primitive(String),
+    Defined {
+        defined: String,
+    },
+    Option {
+        option: Box<IdlType>,
+    },
+    Vec {
+        vec: Box<IdlType>,
+    },
+    Array {
+        array: (Box<IdlType>, usize),
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlTypeDef {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlTypeDefTy,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlTypeDefTy {
+    Struct {
+        fields: Vec<IdlField>,
+    },
+    Enum {
+        variants: Vec<IdlEnumVariant>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlEnumVariant {
+    pub name: String,
+    pub fields: Option<IdlEnumFields>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlEnumFields {
+    Named(Vec<IdlField>),
+    Tuple(Vec<IdlType>),
+}
+
+/// An instruction decoded against the IDL: Borsh args as JSON (field names
+/// as in the IDL, 128-bit integers as strings, pubkeys as base58) and the
+/// instruction's accounts labelled with their IDL names.
+#[derive(Debug, Clone)]
+pub struct DecodedInstruction {
+    pub name: String,
+    pub args: Value,
+    pub accounts: Vec<(String, Pubkey)>,
+}
+
+impl DecodedInstruction {
+    pub fn account(&self, name: &str) -> Option<Pubkey> {
+        self.accounts
+            .iter()
+            .find(|(account, _)| account == name)
+            .map(|(_, pubkey)| *pubkey)
+    }
+
+    /// Looks up an argument by JSON pointer, e.g. `/data/tokenAmount`.
+    pub fn arg(&self, pointer: &str) -> Option<&Value> {
+        self.args.pointer(pointer)
+    }
+
+    pub fn arg_u64(&self, pointer: &str) -> Option<u64> {
+        self.arg(pointer)?.as_u64()
+    }
+
+    pub fn arg_str(&self, pointer: &str) -> Option<&str> {
+        self.arg(pointer)?.as_str()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    UnknownDiscriminator([u8; 8]),
+    Truncated {
+        needed: usize,
+        remaining: usize,
+    },
+    BadPubkey(String),
+    Invalid(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownDiscriminator(disc) => write!(f, "unknown discriminator {}", hex::encode(disc)),
+            Self::Truncated { needed, remaining } =>
+                write!(f, "unexpected end of data: need {} bytes, have {}", needed, remaining),
+            Self::BadPubkey(pubkey) => write!(f, "invalid pubkey {}", pubkey),
+            Self::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Anchor discriminator: first 8 bytes of `sha256("<namespace>:<name>")`.
+pub fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let mut disc = [0u8; 8];
+    disc.copy_from_slice(&hash(format!("{}:{}", namespace, name).as_bytes()).to_bytes()[..8]);
+    disc
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+pub struct IdlDecoder {
+    idl: Idl,
+    instructions: HashMap<[u8; 8], usize>,
+    accounts: HashMap<[u8; 8], usize>,
+    types: HashMap<String, IdlTypeDef>,
+}
+
+impl IdlDecoder {
+    pub fn new(idl: Idl) -> Self {
+        let instructions = idl.instructions
+            .iter()
+            .enumerate()
+            .map(|(i, ix)| (discriminator("global", &to_snake_case(&ix.name)), i))
+            .collect();
+        let accounts = idl.accounts
+            .iter()
+            .enumerate()
+            .map(|(i, account)| (discriminator("account", &account.name), i))
+            .collect();
+        let types = idl.types
+            .iter()
+            .chain(idl.accounts.iter())
+            .map(|def| (def.name.clone(), def.clone()))
+            .collect();
+        Self { idl, instructions, accounts, types }
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(Self::new(serde_json::from_str(json)?))
+    }
+
+    pub fn idl(&self) -> &Idl {
+        &self.idl
+    }
+
+    /// Decodes raw instruction data and labels `accounts` (base58 strings,
+    /// in instruction order) with the IDL account names.
+    pub fn decode_instruction(
+        &self,
+        data: &[u8],
+        accounts: &[String]
+    ) -> Result<DecodedInstruction, DecodeError> {
+        let mut reader = BorshReader::new(data);
+        let disc: [u8; 8] = reader.take()?;
+        let Some(&index) = self.instructions.get(&disc) else {
+            return Err(DecodeError::UnknownDiscriminator(disc));
+        };
+        let instruction = &self.idl.instructions[index];
+
+        let mut args = Map::new();
+        for field in &instruction.args {
+            args.insert(field.name.clone(), self.decode_type(&field.ty, &mut reader)?);
+        }
+
+        let accounts = instruction.accounts
+            .iter()
+            .zip(accounts)
+            .map(|(account, pubkey)| {
+                Pubkey::from_str(pubkey)
+                    .map(|pubkey| (account.name.clone(), pubkey))
+                    .map_err(|_| DecodeError::BadPubkey(pubkey.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(DecodedInstruction {
+            name: instruction.name.clone(),
+            args: Value::Object(args),
+            accounts,
+        })
+    }
+
+    /// Decodes an account owned by the program, returning its IDL type name
+    /// and its fields as JSON.
+    pub fn decode_account(&self, data: &[u8]) -> Result<(String, Value), DecodeError> {
+        let mut reader = BorshReader::new(data);
+        let disc: [u8; 8] = reader.take()?;
+        let Some(&index) = self.accounts.get(&disc) else {
+            return Err(DecodeError::UnknownDiscriminator(disc));
+        };
+        let account = &self.idl.accounts[index];
+        Ok((account.name.clone(), self.decode_type_def(account, &mut reader)?))
+    }
+
+    fn decode_type(&self, ty: &IdlType, reader: &mut BorshReader) -> Result<Value, DecodeError> {
+        Ok(match ty {
+            IdlType::Primitive(name) =>
+                match name.as_str() {
+                    "bool" => json!(reader.take::<1>()?[0] != 0),
+                    "u8" => json!(reader.take::<1>()?[0]),
+                    "i8" => json!(reader.take::<1>()?[0] as i8),
+                    "u16" => json!(u16::from_le_bytes(reader.take()?)),
+                    "i16" => json!(i16::from_le_bytes(reader.take()?)),
+                    "u32" => json!(u32::from_le_bytes(reader.take()?)),
+                    "i32" => json!(i32::from_le_bytes(reader.take()?)),
+                    "u64" => json!(u64::from_le_bytes(reader.take()?)),
+                    "i64" => json!(i64::from_le_bytes(reader.take()?)),
+                    "u128" => json!(u128::from_le_bytes(reader.take()?).to_string()),
+                    "i128" => json!(i128::from_le_bytes(reader.take()?).to_string()),
+                    "publicKey" => json!(reader.pubkey()?.to_string()),
+                    "string" => json!(reader.string()?),
+                    "bytes" => {
+                        let len = u32::from_le_bytes(reader.take()?) as usize;
+                        json!(hex::encode(reader.take_slice(len)?))
+                    }
+                    other => {
+                        return Err(DecodeError::Invalid(format!("unsupported IDL type {}", other)));
+                    }
+                }
+            IdlType::Option { option } => {
+                if reader.take::<1>()?[0] == 0 {
+                    Value::Null
+                } else {
+                    self.decode_type(option, reader)?
+                }
+            }
+            IdlType::Vec { vec } => {
+                let len = u32::from_le_bytes(reader.take()?) as usize;
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    items.push(self.decode_type(vec, reader)?);
+                }
+                Value::Array(items)
+            }
+            IdlType::Array { array: (item, len) } => {
+                let mut items = Vec::with_capacity(*len);
+                for _ in 0..*len {
+                    items.push(self.decode_type(item, reader)?);
+                }
+                Value::Array(items)
+            }
+            IdlType::Defined { defined } => {
+                let Some(def) = self.types.get(defined) else {
+                    return Err(DecodeError::Invalid(format!("undefined IDL type {}", defined)));
+                };
+                self.decode_type_def(def, reader)?
+            }
+        })
+    }
+
+    fn decode_type_def(
+        &self,
+        def: &IdlTypeDef,
+        reader: &mut BorshReader
+    ) -> Result<Value, DecodeError> {
+        match &def.ty {
+            IdlTypeDefTy::Struct { fields } => self.decode_fields(fields, reader),
+            IdlTypeDefTy::Enum { variants } => {
+                let tag = reader.take::<1>()?[0] as usize;
+                let Some(variant) = variants.get(tag) else {
+                    return Err(
+                        DecodeError::Invalid(format!("invalid variant {} for enum {}", tag, def.name))
+                    );
+                };
+                Ok(match &variant.fields {
+                    None => json!(variant.name),
+                    Some(IdlEnumFields::Named(fields)) =>
+                        json!({ variant.name.clone(): self.decode_fields(fields, reader)? }),
+                    Some(IdlEnumFields::Tuple(types)) => {
+                        let mut items = Vec::new();
+                        for ty in types {
+                            items.push(self.decode_type(ty, reader)?);
+                        }
+                        json!({ variant.name.clone(): items })
+                    }
+                })
+            }
+        }
+    }
+
+    fn decode_fields(
+        &self,
+        fields: &[IdlField],
+        reader: &mut BorshReader
+    ) -> Result<Value, DecodeError> {
+        let mut map = Map::new();
+        for field in fields {
+            map.insert(field.name.clone(), self.decode_type(&field.ty, reader)?);
+        }
+        Ok(Value::Object(map))
+    }
+}
+
+/// Cursor over Borsh-encoded bytes.
+pub(crate) struct BorshReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> BorshReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub(crate) fn take_slice(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.data.len() < len {
+            return Err(DecodeError::Truncated { needed: len, remaining: self.data.len() });
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.take_slice(N)?);
+        Ok(bytes)
+    }
+
+    pub(crate) fn pubkey(&mut self) -> Result<Pubkey, DecodeError> {
+        Ok(Pubkey::new_from_array(self.take()?))
+    }
+
+    /// A `u32` length-prefixed UTF-8 string.
+    pub(crate) fn string(&mut self) -> Result<String, DecodeError> {
+        let len = u32::from_le_bytes(self.take()?) as usize;
+        let bytes = self.take_slice(len)?.to_vec();
+        String::from_utf8(bytes).map_err(|e| DecodeError::Invalid(e.to_string()))
+    }
+}
+
+/// Decoder for the bundled Moonshot IDL, parsed once on first use.
+pub fn moonshot() -> &'static IdlDecoder {
+    static DECODER: OnceLock<IdlDecoder> = OnceLock::new();
+    DECODER.get_or_init(|| {
+        IdlDecoder::from_json(MOONSHOT_IDL).expect("bundled Moonshot IDL is invalid")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_IDL: &str = r#"{
+        "version": "0.1.0",
+        "name": "test",
+        "instructions": [
+            {
+                "name": "placeOrder",
+                "accounts": [{ "name": "owner", "isMut": true, "isSigner": true }, { "name": "market" }],
+                "args": [
+                    { "name": "order", "type": { "defined": "Order" } },
+                    { "name": "memo", "type": { "option": "string" } }
+                ]
+            }
+        ],
+        "accounts": [
+            { "name": "Market", "type": { "kind": "struct", "fields": [{ "name": "total", "type": "u128" }] } }
+        ],
+        "types": [
+            {
+                "name": "Order",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "side", "type": { "defined": "Side" } },
+                        { "name": "prices", "type": { "vec": "u64" } },
+                        { "name": "flags", "type": { "array": ["bool", 2] } },
+                        { "name": "limit", "type": { "option": "i64" } }
+                    ]
+                }
+            },
+            {
+                "name": "Side",
+                "type": {
+                    "kind": "enum",
+                    "variants": [
+                        { "name": "Bid" },
+                        { "name": "Ask", "fields": [{ "name": "tick", "type": "u16" }] },
+                        { "name": "Both", "fields": ["u8", "u8"] }
+                    ]
+                }
+            }
+        ]
+    }"#;
+
+    fn decoder() -> IdlDecoder {
+        IdlDecoder::from_json(TEST_IDL).unwrap()
+    }
+
+    fn accounts() -> Vec<String> {
+        vec![Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string()]
+    }
+
+    /// `placeOrder` data: an `Ask` order at tick 7 with two prices, flags
+    /// `[true, false]`, no limit and the memo `hi`.
+    fn place_order_data() -> Vec<u8> {
+        let mut data = discriminator("global", "place_order").to_vec();
+        data.push(1);
+        data.extend_from_slice(&7u16.to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&100u64.to_le_bytes());
+        data.extend_from_slice(&200u64.to_le_bytes());
+        data.extend_from_slice(&[1, 0]);
+        data.push(0);
+        data.push(1);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(b"hi");
+        data
+    }
+
+    #[test]
+    fn reader_takes_in_order_and_reports_truncation() {
+        let mut data = 5u32.to_le_bytes().to_vec();
+        data.extend_from_slice(b"moon");
+        let mut reader = BorshReader::new(&data);
+        assert_eq!(reader.take::<2>().unwrap(), [5, 0]);
+        assert_eq!(reader.take_slice(2).unwrap(), &[0, 0]);
+        assert_eq!(reader.take_slice(5), Err(DecodeError::Truncated { needed: 5, remaining: 4 }));
+
+        let mut reader = BorshReader::new(&data);
+        assert_eq!(reader.string(), Err(DecodeError::Truncated { needed: 5, remaining: 4 }));
+        let mut reader = BorshReader::new(&[0u8; 31]);
+        assert_eq!(reader.pubkey(), Err(DecodeError::Truncated { needed: 32, remaining: 31 }));
+    }
+
+    #[test]
+    fn reader_rejects_invalid_utf8() {
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0xff, 0xfe]);
+        assert!(matches!(BorshReader::new(&data).string(), Err(DecodeError::Invalid(_))));
+    }
+
+    #[test]
+    fn decodes_nested_types() {
+        let accounts = accounts();
+        let decoded = decoder().decode_instruction(&place_order_data(), &accounts).unwrap();
+        assert_eq!(decoded.name, "placeOrder");
+        assert_eq!(
+            decoded.args,
+            json!({
+                "order": { "side": { "Ask": { "tick": 7 } }, "prices": [100, 200], "flags": [true, false], "limit": null },
+                "memo": "hi"
+            })
+        );
+        assert_eq!(decoded.account("owner").map(|owner| owner.to_string()), Some(accounts[0].clone()));
+        assert_eq!(decoded.account("market").map(|market| market.to_string()), Some(accounts[1].clone()));
+    }
+
+    #[test]
+    fn decodes_unit_and_tuple_variants() {
+        let decoder = decoder();
+        let mut data = discriminator("global", "place_order").to_vec();
+        data.extend_from_slice(&[2, 3, 4]);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 1]);
+        data.extend_from_slice(&(-5i64).to_le_bytes());
+        data.push(0);
+        let decoded = decoder.decode_instruction(&data, &accounts()).unwrap();
+        assert_eq!(decoded.arg("/order/side"), Some(&json!({ "Both": [3, 4] })));
+        assert_eq!(decoded.arg("/order/limit"), Some(&json!(-5)));
+
+        data[8] = 0;
+        data.splice(9..11, []);
+        let decoded = decoder.decode_instruction(&data, &accounts()).unwrap();
+        assert_eq!(decoded.arg("/order/side"), Some(&json!("Bid")));
+
+        data[8] = 3;
+        assert!(matches!(decoder.decode_instruction(&data, &accounts()), Err(DecodeError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_unknown_discriminators_and_truncated_data() {
+        let decoder = decoder();
+        let unknown = discriminator("global", "cancel_order");
+        assert_eq!(decoder.decode_instruction(&unknown, &accounts()).unwrap_err(), DecodeError::UnknownDiscriminator(unknown));
+        // The camelCase name itself isn't what Anchor hashes
+        let camel = discriminator("global", "placeOrder");
+        assert_eq!(decoder.decode_instruction(&camel, &accounts()).unwrap_err(), DecodeError::UnknownDiscriminator(camel));
+
+        let data = place_order_data();
+        assert!(matches!(decoder.decode_instruction(&data[..data.len() - 1], &accounts()), Err(DecodeError::Truncated { .. })));
+        assert!(matches!(decoder.decode_instruction(&data[..4], &accounts()), Err(DecodeError::Truncated { .. })));
+        let bad_accounts = vec!["not a pubkey".to_string()];
+        assert_eq!(decoder.decode_instruction(&data, &bad_accounts).unwrap_err(), DecodeError::BadPubkey("not a pubkey".to_string()));
+    }
+
+    #[test]
+    fn decodes_accounts() {
+        let mut data = discriminator("account", "Market").to_vec();
+        data.extend_from_slice(&(u64::MAX as u128 + 1).to_le_bytes());
+        let (name, fields) = decoder().decode_account(&data).unwrap();
+        assert_eq!(name, "Market");
+        assert_eq!(fields, json!({ "total": "18446744073709551616" }));
+    }
+
+    #[test]
+    fn decodes_moonshot_migrate_funds() {
+        let accounts: Vec<String> = (0..12).map(|_| Pubkey::new_unique().to_string()).collect();
+        let data = discriminator("global", "migrate_funds");
+        let decoded = moonshot().decode_instruction(&data, &accounts).unwrap();
+        assert_eq!(decoded.name, "migrateFunds");
+        assert_eq!(decoded.args, json!({}));
+        assert_eq!(decoded.account("migrationAuthority").map(|key| key.to_string()), Some(accounts[1].clone()));
+        assert_eq!(decoded.account("curveAccount").map(|key| key.to_string()), Some(accounts[2].clone()));
+        assert_eq!(decoded.account("mint").map(|key| key.to_string()), Some(accounts[5].clone()));
+    }
+
+    #[test]
+    fn decodes_moonshot_config_instructions() {
+        let authority = Pubkey::new_unique();
+        let mut data = discriminator("global", "config_update").to_vec();
+        data.push(0);
+        data.push(0);
+        data.push(1);
+        data.extend_from_slice(authority.as_ref());
+        data.extend_from_slice(&[0, 0]);
+        data.push(1);
+        data.extend_from_slice(&100u16.to_le_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data.push(1);
+        data.extend_from_slice(&500_000_000_000u64.to_le_bytes());
+        data.extend_from_slice(&[1, 0]);
+        data.extend_from_slice(&[0, 0, 0, 0, 0]);
+        let accounts: Vec<String> = (0..2).map(|_| Pubkey::new_unique().to_string()).collect();
+
+        let decoded = moonshot().decode_instruction(&data, &accounts).unwrap();
+        assert_eq!(decoded.name, "configUpdate");
+        assert_eq!(decoded.arg_str("/data/configAuthority"), Some(authority.to_string().as_str()));
+        assert_eq!(decoded.arg_u64("/data/feeBps"), Some(100));
+        assert_eq!(decoded.arg_u64("/data/marketcapThreshold"), Some(500_000_000_000));
+        assert_eq!(decoded.arg("/data/marketcapCurrency"), Some(&json!("Sol")));
+        assert_eq!(decoded.arg("/data/migrationAuthority"), Some(&Value::Null));
+        assert_eq!(decoded.arg("/data/coefB"), Some(&Value::Null));
+
+        data[..8].copy_from_slice(&discriminator("global", "config_init"));
+        let accounts: Vec<String> = (0..3).map(|_| Pubkey::new_unique().to_string()).collect();
+        let decoded = moonshot().decode_instruction(&data, &accounts).unwrap();
+        assert_eq!(decoded.name, "configInit");
+        assert_eq!(decoded.account("configAccount").map(|key| key.to_string()), Some(accounts[1].clone()));
+    }
+}
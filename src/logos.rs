@@ -0,0 +1,154 @@
+use std::{ collections::VecDeque, io::Cursor, str::FromStr, sync::{ Arc, Mutex } };
+
+use image::{ imageops::FilterType, ImageReader, Limits };
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{ config::LogoConfig, storage::Storage };
+
+/// Largest width or height of an image that is decoded.
+const MAX_DIMENSION: u32 = 4096;
+/// Memory the decoder may allocate for one image.
+const MAX_DECODE_ALLOC: u64 = 128 * 1024 * 1024;
+/// Hashes with fewer set (or unset) bits than this come from flat or
+/// near-blank images, which all look alike to a dHash.
+const MIN_HASH_BITS: u32 = 8;
+
+/// 64-bit difference hash (dHash) of an image: the image is shrunk to 9x8
+/// grayscale pixels and each bit records whether a pixel is darker than its
+/// right neighbour. Re-encoding, resizing and small edits flip few bits.
+pub fn image_hash(bytes: &[u8]) -> anyhow::Result<u64> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    reader.limits(limits);
+    let pixels = reader.decode()?.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if pixels.get_pixel(x, y)[0] < pixels.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Ok(hash)
+}
+
+/// Number of bits two hashes differ in, from 0 (same image) to 64.
+pub fn hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Whether `hash` has enough gradients in it to tell images apart.
+fn informative(hash: u64) -> bool {
+    let ones = hash.count_ones();
+    (MIN_HASH_BITS..=64 - MIN_HASH_BITS).contains(&ones)
+}
+
+/// An earlier launch whose image matches a new one's.
+#[derive(Debug, Clone, Copy)]
+pub struct LogoMatch {
+    pub mint: Pubkey,
+    /// Bits the two image hashes differ in
+    pub distance: u32,
+}
+
+/// Image hashes of the most recent launches, oldest first, for spotting
+/// launches that reuse an earlier token's logo. Seeded from the database at
+/// startup when storage is enabled.
+pub struct LogoIndex {
+    config: LogoConfig,
+    hashes: Mutex<VecDeque<(Pubkey, u64)>>,
+    storage: Option<Arc<Storage>>,
+}
+
+impl LogoIndex {
+    pub fn load(config: LogoConfig, storage: Option<Arc<Storage>>) -> anyhow::Result<Self> {
+        let mut hashes = VecDeque::with_capacity(config.recent_launches);
+        if let Some(storage) = &storage {
+            // Newest first; keep the order launches arrive in
+            for (mint, hash) in storage.image_hashes(config.recent_launches)?.into_iter().rev() {
+                let Ok(mint) = Pubkey::from_str(&mint) else {
+                    continue;
+                };
+                if informative(hash) {
+                    hashes.push_back((mint, hash));
+                }
+            }
+        }
+        Ok(Self { config, hashes: Mutex::new(hashes), storage })
+    }
+
+    /// Records `mint`'s image hash and returns the earliest other recent
+    /// launch whose image is within `max_distance` bits of it. Hashes of
+    /// near-blank images are stored but never matched.
+    pub fn insert(&self, mint: Pubkey, image: &str, hash: u64, slot: u64) -> Option<LogoMatch> {
+        let found = if informative(hash) {
+            let mut hashes = self.hashes.lock().unwrap();
+            let found = hashes
+                .iter()
+                .filter(|(other, _)| *other != mint)
+                .map(|(other, other_hash)| LogoMatch { mint: *other, distance: hash_distance(hash, *other_hash) })
+                .find(|found| found.distance <= self.config.max_distance);
+            if !hashes.iter().any(|(other, _)| *other == mint) {
+                hashes.push_back((mint, hash));
+                while hashes.len() > self.config.recent_launches {
+                    hashes.pop_front();
+                }
+            }
+            found
+        } else {
+            None
+        };
+
+        if let Some(storage) = &self.storage {
+            if let Err(e) = storage.record_image_hash(&mint.to_string(), image, hash, slot) {
+                eprintln!("Failed to store image hash of {}: {:#}", mint, e);
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(recent_launches: usize) -> LogoIndex {
+        LogoIndex::load(LogoConfig { recent_launches, ..LogoConfig::default() }, None).unwrap()
+    }
+
+    const HASH: u64 = 0x0f0f_3c3c_5a5a_f0f0;
+
+    #[test]
+    fn matches_nearby_hash() {
+        let logos = index(10);
+        let first = Pubkey::new_unique();
+        assert!(logos.insert(first, "a", HASH, 1).is_none());
+        let found = logos.insert(Pubkey::new_unique(), "b", HASH ^ 0b101, 2).unwrap();
+        assert_eq!(found.mint, first);
+        assert_eq!(found.distance, 2);
+    }
+
+    #[test]
+    fn blank_images_never_match() {
+        let logos = index(10);
+        for hash in [0, 1, u64::MAX, u64::MAX << 2] {
+            assert!(logos.insert(Pubkey::new_unique(), "a", hash, 1).is_none());
+            assert!(logos.insert(Pubkey::new_unique(), "b", hash, 2).is_none());
+        }
+    }
+
+    #[test]
+    fn forgets_launches_past_the_limit() {
+        let logos = index(2);
+        logos.insert(Pubkey::new_unique(), "a", HASH, 1);
+        logos.insert(Pubkey::new_unique(), "b", !HASH, 2);
+        logos.insert(Pubkey::new_unique(), "c", HASH.rotate_left(4), 3);
+        assert!(logos.insert(Pubkey::new_unique(), "d", HASH, 4).is_none());
+    }
+}
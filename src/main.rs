@@ -2,77 +2,550 @@ pub mod ws_client;
 pub mod event;
 pub mod new_tokens;
 pub mod utlis;
-pub mod consts;
+pub mod backfill;
+pub mod idl;
+pub mod curve;
+pub mod pricing;
+pub mod config;
+pub mod storage;
+pub mod trades;
+pub mod alerts;
+pub mod pipeline;
+pub mod creators;
+pub mod reputation;
+mod logos;
+mod clones;
 
-use consts::{RPC_URL, WS_URL};
-use event::MoonEvent;
-use new_tokens::new_tokens_prog;
+use std::{process, sync::{Arc, Mutex}, time::Duration};
+
+use alerts::{dev_sell::DevSell, graduation::Graduation, whale::WhaleTrade, DevTracker, Launch};
+
+use backfill::Backfill;
+use config::Config;
+use creators::CreatorHistoryService;
+use curve::{CurveTracker, Tracked};
+use pricing::PriceService;
+use reputation::Reputation;
+use storage::Storage;
+use trades::{Position, Trade, TradeAggregator};
+use event::{parse_error_counts, CreateEvent, EventEnvelope, MoonEvent, TransactionNotification};
+use new_tokens::{new_tokens_prog, LaunchInsights};
+use pipeline::{Pipeline, Stage};
 use solana_client::nonblocking::rpc_client::RpcClient;
-use tokio_tungstenite::tungstenite::protocol::Message;
-use futures_util::StreamExt;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use futures_util::{pin_mut, StreamExt};
+use logos::{image_hash, LogoIndex, LogoMatch};
+use clones::CloneDetector;
 use serde_json::json;
-use utlis::user_data::{get_pump_token_metadata, get_user_created_tokens};
-use ws_client::subscribe_experimental;
+use utlis::metadata::MetadataResolver;
+use utlis::user_data::{get_token_metadata, TokenMetadata};
+use utlis::embed::{Embed, WebhookClient};
+use ws_client::{subscribe_resubscribing, ReconnectConfig, StreamEvent};
+
+/// Mints without trades for about a day are dropped from the trade aggregates.
+const TRADE_STATS_RETENTION_SLOTS: u64 = 216_000;
+/// Notifications (live and backfilled) waiting to be decoded and applied.
+const INGEST_CAPACITY: usize = 4096;
+
+/// Long-lived clients and state shared by the event handlers.
+#[derive(Clone)]
+struct Services {
+    config: Arc<Config>,
+    rpc_client: Arc<RpcClient>,
+    curves: Arc<CurveTracker>,
+    prices: Arc<PriceService>,
+    storage: Option<Arc<Storage>>,
+    trades: Arc<TradeAggregator>,
+    devs: Arc<DevTracker>,
+    pipeline: Pipeline,
+    creators: Arc<CreatorHistoryService>,
+    metadata: Arc<MetadataResolver>,
+    logos: Arc<LogoIndex>,
+    clones: Arc<CloneDetector>,
+    webhook: Arc<WebhookClient>,
+}
+
+/// Applies a trade to the curve tracker, queueing a fetch of the curve first
+/// if the mint isn't tracked yet. Trades arriving while that fetch is in
+/// flight are held and replayed on top of it.
+fn track_curve(services: &Services, envelope: EventEnvelope) {
+    let (mint, curve_account) = match &envelope.event {
+        MoonEvent::BuyEvent(buy) => (buy.mint, buy.curve_account),
+        MoonEvent::SellEvent(sell) => (sell.mint, sell.curve_account),
+        MoonEvent::CreateEvent(create) => (create.mint, create.curve_account),
+        MoonEvent::Migration(migration) => (migration.mint, migration.curve_account),
+    };
+    match services.curves.track(&envelope) {
+        Tracked::Applied => {
+            if let Some(state) = services.curves.get(&mint) {
+                println!("{}", state);
+            }
+            return;
+        }
+        Tracked::Buffered => {
+            return;
+        }
+        Tracked::Fetch => {}
+    }
+
+    let job_services = services.clone();
+    let queued = services.pipeline.submit(async move {
+        let services = job_services;
+        let commitment = services.config.commitment.into();
+        let refresh = services.curves.refresh(&services.rpc_client, &curve_account, commitment);
+        match services.pipeline.stage(Stage::Curve, refresh).await {
+            Some(Ok(state)) => println!("{}", state),
+            Some(Err(e)) => {
+                services.curves.abandon_refresh(&mint);
+                eprintln!("Failed to fetch curve {}: {:#}", curve_account, e);
+            }
+            None => services.curves.abandon_refresh(&mint),
+        }
+    });
+    if !queued {
+        services.curves.abandon_refresh(&mint);
+    }
+}
+
+/// Current SOL/USD rate, if USD pricing is enabled and the rate is known.
+async fn sol_usd(services: &Services) -> Option<f64> {
+    if !services.config.features.usd_pricing {
+        return None;
+    }
+    services.pipeline.stage(Stage::Pricing, services.prices.sol_usd()).await.flatten()
+}
+
+/// Queues an alert: the embed is built with the current SOL/USD rate and
+/// posted to the alerts webhook by a pipeline worker.
+fn post_alert(services: &Services, embed: impl FnOnce(Option<f64>) -> Embed + Send + 'static) {
+    let job_services = services.clone();
+    services.pipeline.submit(async move {
+        let services = job_services;
+        let embed = embed(sol_usd(&services).await);
+        let send = alerts::send_alert(&services.webhook, &services.config.webhooks.alerts, embed);
+        services.pipeline.stage(Stage::Webhook, send).await;
+    });
+}
+
+/// Looks up who launched the mint, from memory or the database, as of
+/// `slot`.
+fn launch(services: &Services, mint: &Pubkey, slot: u64) -> Option<Launch> {
+    if let Some(launch) = services.devs.launch(mint, slot) {
+        return Some(launch);
+    }
+    if services.devs.is_unknown(mint) {
+        return None;
+    }
+    let token = match services.storage.as_ref()?.token(&mint.to_string()) {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            services.devs.mark_unknown(*mint);
+            return None;
+        }
+        Err(e) => {
+            eprintln!("Failed to look up token {}: {:#}", mint, e);
+            return None;
+        }
+    };
+    let launch = Launch::try_from(&token).ok()?;
+    services.devs.register(*mint, launch.clone(), slot);
+    Some(launch)
+}
+
+/// Posts an alert if the sell in `envelope` was made by the mint's creator.
+fn alert_dev_sell(services: &Services, envelope: &EventEnvelope, position: Position) {
+    let MoonEvent::SellEvent(sell) = &envelope.event else {
+        return;
+    };
+    let Some(launch) = launch(services, &sell.mint, envelope.context.slot) else {
+        return;
+    };
+    let Some(dev_sell) = DevSell::detect(&launch, envelope, position) else {
+        return;
+    };
+    if dev_sell.percent_of_position < services.config.alerts.dev_sell.min_percent {
+        return;
+    }
+    println!("Dev sell {}: {:.1}% of position", dev_sell.mint, dev_sell.percent_of_position);
+
+    post_alert(services, move |sol_usd| dev_sell.embed(sol_usd));
+}
+
+/// Posts an alert if the trade in `envelope` reaches the mint's whale threshold.
+fn alert_whale(services: &Services, envelope: &EventEnvelope, position: Position) {
+    let lookup = |trade: &Trade| launch(services, &trade.mint, trade.slot);
+    let Some(whale) = WhaleTrade::detect(envelope, &services.config.alerts.whale, lookup, position) else {
+        return;
+    };
+    let trade = &whale.trade;
+    println!("Whale trade {}: {:.2} SOL by {}", trade.mint, trade.sol(), trade.trader);
+
+    post_alert(services, move |sol_usd| whale.embed(sol_usd));
+}
+
+/// Hashes the launch image and looks for an earlier launch with the same
+/// logo.
+async fn match_logo(services: &Services, mint: &Pubkey, image: &str, slot: u64) -> Option<LogoMatch> {
+    if image.is_empty() {
+        return None;
+    }
+    let download = services.metadata.fetch_bytes(image, services.config.logos.max_bytes);
+    let bytes = match services.pipeline.stage(Stage::Image, download).await? {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to download image of {}: {:#}", mint, e);
+            return None;
+        }
+    };
+    let hash = match tokio::task::spawn_blocking(move || image_hash(&bytes)).await {
+        Ok(Ok(hash)) => hash,
+        Ok(Err(e)) => {
+            eprintln!("Failed to hash image of {}: {:#}", mint, e);
+            return None;
+        }
+        Err(e) => {
+            eprintln!("Image hashing of {} panicked: {}", mint, e);
+            return None;
+        }
+    };
+    let logo_match = services.logos.insert(*mint, image, hash, slot);
+    if let Some(logo_match) = &logo_match {
+        println!("{} reuses the image of {} ({} bits differ)", mint, logo_match.mint, logo_match.distance);
+    }
+    logo_match
+}
+
+/// Fetches a launch's metadata, then its logo match, on-chain metadata and
+/// the creator's earlier tokens side by side, and posts the launch embed.
+/// Runs on a pipeline worker.
+async fn announce_launch(services: Services, create_event: CreateEvent, slot: u64) {
+    let clone_match = if services.config.features.clone_detection {
+        services.clones.check(&create_event)
+    } else {
+        None
+    };
+    if let Some(clone_match) = &clone_match {
+        println!("{}: {}", create_event.mint, clone_match);
+    }
+
+    let resolve = services.metadata.resolve(&create_event.uri, &create_event.name, &create_event.symbol);
+    let token_data = services.pipeline
+        .stage(Stage::Metadata, resolve).await
+        .unwrap_or_else(|| {
+            TokenMetadata::unavailable(&create_event.name, &create_event.symbol, "timed out".to_string())
+        });
+    if let Some(storage) = &services.storage {
+        let result = match &token_data.unavailable {
+            Some(reason) => Err(reason.as_str()),
+            None => Ok(&token_data),
+        };
+        if let Err(e) = storage.record_metadata_fetch(&create_event.mint.to_string(), &create_event.uri, result) {
+            eprintln!("Failed to store metadata fetch: {:#}", e);
+        }
+    }
+
+    // Independent of each other; the image only needs the JSON metadata
+    let logo_lookup = async {
+        if services.config.features.logo_matching {
+            match_logo(&services, &create_event.mint, &token_data.image, slot).await
+        } else {
+            None
+        }
+    };
+    let onchain_lookup = async {
+        let lookup = get_token_metadata(&services.rpc_client, &create_event.mint, services.config.commitment.into());
+        match services.pipeline.stage(Stage::OnchainMetadata, lookup).await {
+            Some(Ok(metadata)) => Some(metadata),
+            Some(Err(e)) => {
+                eprintln!("Failed to read on-chain metadata of {}: {:#}", create_event.mint, e);
+                None
+            }
+            None => None,
+        }
+    };
+    let creator_lookup = async {
+        if !services.config.features.creator_history {
+            return None;
+        }
+        let lookup = services.creators.history(&services.rpc_client, &create_event.sender);
+        match services.pipeline.stage(Stage::CreatorHistory, lookup).await {
+            Some(Ok(history)) => {
+                println!(
+                    "Creator {} launched {} tokens: {:?}",
+                    history.creator,
+                    history.launch_count(),
+                    history.mints().collect::<Vec<_>>()
+                );
+                Some(history)
+            }
+            Some(Err(e)) => {
+                eprintln!("Failed to look up creator {}: {:#}", create_event.sender, e);
+                None
+            }
+            None => None,
+        }
+    };
+    let (logo_match, onchain_metadata, creator_history) = tokio::join!(logo_lookup, onchain_lookup, creator_lookup);
+
+    let reputation = creator_history
+        .as_ref()
+        .map(|history| Reputation::score(history, &create_event.mint, &services.config.reputation));
+    println!("token_data {:?}", token_data);
+    if let Some(reputation) = &reputation {
+        println!("Creator {} reputation {}", create_event.sender, reputation);
+        if !services.config.alerts.launch.allows(reputation) {
+            println!("Not posting {}: creator filtered out", create_event.mint);
+            return;
+        }
+    }
+
+    let sol_usd = sol_usd(&services).await;
+    let send = new_tokens_prog(
+        create_event,
+        token_data,
+        LaunchInsights { onchain_metadata, creator_history, reputation, logo_match, clone_match },
+        sol_usd,
+        &services.webhook,
+        services.config.webhooks.launches.clone(),
+    );
+    services.pipeline.stage(Stage::Webhook, send).await;
+}
+
+/// Decodes a notification and updates the in-memory state and database in
+/// order; anything that needs the network is queued on the pipeline.
+fn handle_notification(
+    message_obj: serde_json::Value,
+    backfill: &Mutex<Backfill>,
+    services: &Services,
+) {
+    let notification = match TransactionNotification::from_value(message_obj) {
+        Ok(notification) => notification,
+        Err(e) => {
+            eprintln!("Skipping message: {}", e);
+            return;
+        }
+    };
+    if services.config.features.backfill && !backfill.lock().unwrap().observe(&notification) {
+        return;
+    }
+
+    println!("true");
+    let events = match event::parse_pump_event(notification, &services.config.program_id) {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("Failed to parse transaction: {}", e);
+            return;
+        }
+    };
+    for envelope in events {
+        if let Some(storage) = &services.storage {
+            if let Err(e) = storage.record_event(&envelope) {
+                eprintln!("Failed to store event {}: {:#}", envelope.context.signature, e);
+            }
+        }
+        match envelope.event.clone() {
+            MoonEvent::BuyEvent(_) | MoonEvent::SellEvent(_) => {
+                if let Some(update) = services.trades.record(&envelope) {
+                    println!("{}", update);
+                    if services.config.alerts.dev_sell.enabled {
+                        alert_dev_sell(services, &envelope, update.position);
+                    }
+                    if services.config.alerts.whale.enabled {
+                        alert_whale(services, &envelope, update.position);
+                    }
+                }
+                if services.config.features.curve_tracking {
+                    track_curve(services, envelope);
+                }
+            }
+            MoonEvent::Migration(migration) => {
+                match migration.pool {
+                    Some((dex, pool)) => println!("{} migrated to {} pool {}", migration.mint, dex, pool),
+                    None => println!("{} migrated", migration.mint),
+                }
+                services.creators.record_migration(&migration.mint);
+                let graduation = Graduation {
+                    signature: envelope.context.signature.clone(),
+                    launch: launch(services, &migration.mint, envelope.context.slot),
+                    curve: services.curves.remove(&migration.mint),
+                    stats: services.trades.get(&migration.mint),
+                    migration,
+                };
+                if services.config.alerts.graduation.enabled {
+                    post_alert(services, move |sol_usd| graduation.embed(sol_usd));
+                }
+            }
+            MoonEvent::CreateEvent(create_event) => {
+                println!("{:?} {:?}", envelope.context, create_event);
+                services.devs.register(create_event.mint, Launch::from(&create_event), envelope.context.slot);
+                let slot = envelope.context.slot;
+                services.pipeline.submit(announce_launch(services.clone(), create_event, slot));
+            }
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {:#}", e);
+            process::exit(2);
+        }
+    };
+
     let method = "transactionSubscribe";
     let params =
         json!([
         {
             "failed": false,
             "accounts": {
-                "include": ["MoonCVVNZFSYkqNXP6bxHLPL6QQJiMagDL3qcqUQTrG"]
+                "include": [config.program_id.to_string()]
             }
         },
         {
-            "commitment": "processed",
+            "commitment": config.commitment.to_string(),
             "encoding": "jsonParsed",
             "transactionDetails": "full",
             "maxSupportedTransactionVersion": 0
         }
     ]);
 
-    let (_, mut stream) = subscribe_experimental(WS_URL, method, params).await.unwrap();
-    
-    
-        while let Some(message) = stream.next().await {
+    let prices = match PriceService::new(config.pricing.source.clone(), config.pricing.ttl) {
+        Ok(prices) => prices,
+        Err(e) => {
+            eprintln!("Failed to create price service: {:#}", e);
+            process::exit(1);
+        }
+    };
+    let metadata = match MetadataResolver::new(config.metadata.clone()) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            eprintln!("Failed to create metadata resolver: {:#}", e);
+            process::exit(1);
+        }
+    };
+    let storage = if config.features.storage {
+        match Storage::open(&config.database) {
+            Ok(storage) => Some(Arc::new(storage)),
+            Err(e) => {
+                eprintln!("Failed to open database {}: {:#}", config.database.display(), e);
+                process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    let webhook = match WebhookClient::new(config.pipeline.timeout(Stage::Webhook) * 3 / 4) {
+        Ok(webhook) => webhook,
+        Err(e) => {
+            eprintln!("Failed to create webhook client: {:#}", e);
+            process::exit(1);
+        }
+    };
+    let logos = match LogoIndex::load(config.logos.clone(), storage.clone()) {
+        Ok(logos) => logos,
+        Err(e) => {
+            eprintln!("Failed to load image hashes: {:#}", e);
+            process::exit(1);
+        }
+    };
+    let clones = match CloneDetector::load(config.clones.clone(), storage.as_deref()) {
+        Ok(clones) => clones,
+        Err(e) => {
+            eprintln!("Failed to load recent launches: {:#}", e);
+            process::exit(1);
+        }
+    };
+    let services = Services {
+        rpc_client: Arc::new(RpcClient::new_with_commitment(config.rpc_url.clone(), CommitmentConfig::confirmed())),
+        curves: Arc::new(CurveTracker::new()),
+        prices: Arc::new(prices),
+        storage: storage.clone(),
+        trades: Arc::new(TradeAggregator::new()),
+        devs: Arc::new(DevTracker::new()),
+        pipeline: Pipeline::start(config.pipeline.clone()),
+        creators: Arc::new(
+            CreatorHistoryService::new(
+                config.program_id,
+                storage.clone(),
+                // Leave room for the metadata lookup that follows the scan
+                config.pipeline.timeout(Stage::CreatorHistory) * 3 / 4,
+                config.commitment.into()
+            )
+        ),
+        metadata: Arc::new(metadata),
+        logos: Arc::new(logos),
+        clones: Arc::new(clones),
+        webhook: Arc::new(webhook),
+        config: Arc::new(config),
+    };
+    let backfill = Arc::new(Mutex::new(Backfill::new(services.config.program_id)));
+
+    // Live and backfilled notifications are applied in arrival order by a
+    // single task, so the websocket is drained while a backfill runs
+    let (ingest, mut ingested) = tokio::sync::mpsc::channel::<serde_json::Value>(INGEST_CAPACITY);
+    let ingest_services = services.clone();
+    let ingest_backfill = backfill.clone();
+    tokio::spawn(async move {
+        while let Some(message_obj) = ingested.recv().await {
+            handle_notification(message_obj, &ingest_backfill, &ingest_services);
+        }
+    });
+
+    let stats_services = services.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(300));
+        loop {
+            interval.tick().await;
+            let counts = parse_error_counts();
+            if counts.iter().any(|(_, count)| *count > 0) {
+                println!("Parse errors: {:?}", counts);
+            }
+            println!("Pipeline: {}", stats_services.pipeline.stats());
+            for (mint, stats) in stats_services.trades.top_by_volume(5) {
+                println!("Top volume {}: {}", mint, stats);
+            }
+            if let Ok(slot) = stats_services.rpc_client.get_slot().await {
+                let min_slot = slot.saturating_sub(TRADE_STATS_RETENTION_SLOTS);
+                stats_services.trades.prune(min_slot);
+                stats_services.devs.prune(min_slot);
+                stats_services.curves.prune(min_slot);
+            }
+        }
+    });
+
+    let stream = subscribe_resubscribing(&services.config.ws_url, method, params, ReconnectConfig::default());
+    pin_mut!(stream);
+
+    while let Some(message) = stream.next().await {
         match message {
-            Ok(Message::Text(data)) => {
-                let message_obj: serde_json::Value = serde_json::from_str(&data).unwrap();
-                println!("true");
-                match event::parse_pump_event(message_obj.clone()).await {
-                    Some(event) =>
-                    match event {
-                        MoonEvent::BuyEvent(_) => {
-                        }
-                        MoonEvent::SellEvent(_) => {
-                        }
-                        MoonEvent::CreateEvent(create_event) => {
-                            println!("{:?}", create_event);
-                            let rpc_client: RpcClient = RpcClient::new(RPC_URL.to_string());
-                            let token_data=get_pump_token_metadata(&create_event.uri).await.unwrap();
-                                let token_accounts = get_user_created_tokens(create_event.sender, rpc_client).await;
-                                // let token_accounts= Vec::new();
-
-                                println!("token_accounts {:?}", token_accounts);
-                                println!("token_data {:?}", token_data);
-                                let task1 = tokio::spawn(new_tokens_prog(create_event.clone(), token_data.clone(),token_accounts.clone()));
-
-                                let _ = tokio::try_join!(task1, 
-                                );
-                            }
-                        }
-                    None => {
-                        continue;
+            StreamEvent::Notification(message_obj) => {
+                if ingest.send(message_obj).await.is_err() {
+                    eprintln!("Ingest task stopped");
+                    process::exit(1);
+                }
+            }
+            StreamEvent::Subscribed(id) => println!("Subscribed to {} (id {})", method, id),
+            StreamEvent::Disconnected(reason) => eprintln!("WebSocket disconnected: {}", reason),
+            StreamEvent::Resubscribed(id) if !services.config.features.backfill => {
+                println!("Resubscribed to {} (id {})", method, id);
+            }
+            StreamEvent::Resubscribed(id) => {
+                let last_slot = backfill.lock().unwrap().last_slot();
+                println!("Resubscribed to {} (id {}), backfilling from slot {:?}", method, id, last_slot);
+                let rpc_client = services.rpc_client.clone();
+                let commitment = services.config.commitment.into();
+                let backfill = backfill.clone();
+                let ingest = ingest.clone();
+                tokio::spawn(async move {
+                    match backfill::recover(&backfill, &rpc_client, commitment, &ingest).await {
+                        Ok(queued) => println!("Queued {} backfilled transactions", queued),
+                        Err(e) => eprintln!("Backfill failed: {:#}", e),
                     }
-                };
+                });
             }
-
-            Err(e) => eprintln!("Error: {}", e),
-            _ => {}
         }
     }
-    stream.close(None).await.unwrap();
-    println!("WebSocket connection closed");
 }
@@ -1,79 +1,161 @@
 
-use crate::{ consts::DISCORD_URL, event::CreateEvent, utlis::{embed::{send_embed, Embed, EmbedField, EmbedThumbnail, WebhookMessage}, user_data::TokenMetadata}};
-use mpl_token_metadata::accounts::Metadata;
-
-
+use crate::{ clones::CloneMatch, creators::{CreatorHistory, LaunchOutcome}, event::CreateEvent, logos::LogoMatch, pricing::format_sol_usd, reputation::Reputation, utlis::{embed::{Embed, EmbedField, EmbedThumbnail, WebhookClient, WebhookMessage}, user_data::{OnchainMetadata, TokenMetadata}}};
+
+/// Earlier launches of the creator listed in the embed.
+const MAX_LISTED_LAUNCHES: usize = 20;
+
+/// What the monitor found out about a launch beyond its create event and
+/// metadata JSON; anything it couldn't look up is `None`.
+#[derive(Debug, Default)]
+pub struct LaunchInsights {
+    pub onchain_metadata: Option<OnchainMetadata>,
+    pub creator_history: Option<CreatorHistory>,
+    pub reputation: Option<Reputation>,
+    /// Earlier launch with the same image
+    pub logo_match: Option<LogoMatch>,
+    /// Watchlist entry or earlier launch the name or symbol copies
+    pub clone_match: Option<CloneMatch>,
+}
 
 pub async fn new_tokens_prog(
     create_event: CreateEvent,
     token_data: TokenMetadata,
-    user_prev_tokens: Vec<Metadata>,
+    insights: LaunchInsights,
+    sol_usd: Option<f64>,
+    webhook: &WebhookClient,
+    webhook_url: String,
 ) {
-
-let amount=create_event.buy_event.unwrap().amount;
-    let percentage = (amount as f64) / 1000000000000000000.0;
-
-    // let token_market_cap =
-    //     ((trade_event.virtual_sol_reserves as f64) / (trade_event.virtual_token_reserves as f64)) *
-    //     1_000_000.0 *
-    //     sol_to_usd_rate;
-
-    let tokens_section: Vec<String> = if !user_prev_tokens.is_empty() {
-        let tokens_result = (async {
-            let mut tokens = Vec::new();
-            for item in &user_prev_tokens {
-                if item.mint == create_event.mint {
-                    continue;
-                } else if !item.mint.to_string().is_empty() {
-        
-
-                    tokens.push(
-                        format!(
-                            "- [ {} $({})]({}) \n",
-                            item.name,
-                            item.symbol,
-                            format!("https://dexscreener.com/solana/{}", item.mint)
-                        )
-                    );
-                }
-            }
-            tokens
-        }).await;
-
-        tokens_result
-    } else {
-        Vec::new()
+    let message = WebhookMessage {
+        content: "".to_string(), 
+        embeds: vec![launch_embed(&create_event, &token_data, insights, sol_usd)],
     };
+    match webhook.send(&webhook_url, &message).await {
+        Ok(()) => println!("Posted launch {}", create_event.mint),
+        Err(e) => eprintln!("Dropped launch embed for {}: {}", create_event.mint, e),
+    }
+}
 
+fn launch_embed(create_event: &CreateEvent, token_data: &TokenMetadata, insights: LaunchInsights, sol_usd: Option<f64>) -> Embed {
+    let LaunchInsights { onchain_metadata, creator_history, reputation, logo_match, clone_match } = insights;
 
-    let mut embed_fields = vec![];
+let amount=create_event.buy_event.as_ref().map_or(0, |buy| buy.amount);
+    let percentage = (amount as f64) / 1000000000000000000.0;
 
-    for chunk in tokens_section.chunks(10) {
-        let chunk_value = chunk.join(""); 
-        embed_fields.push(EmbedField {
-            name: "".to_string(), 
-            value: format!("{}", chunk_value),
-            inline: false,
-        });
+    let market_cap = create_event
+        .market_cap_sol()
+        .map_or("Unknown".to_string(), |market_cap| format_sol_usd(market_cap, sol_usd));
+    let dev_buy = create_event
+        .buy_event
+        .as_ref()
+        .and_then(|buy| buy.execution)
+        .map_or("None".to_string(), |execution| format_sol_usd(execution.sol(), sol_usd));
+
+    let tokens_section: Vec<String> = creator_history
+        .as_ref()
+        .map(|history| {
+            history
+                .others(&create_event.mint)
+                .map(|launch| {
+                    format!(
+                        "- [ {} $({})](https://dexscreener.com/solana/{}) {} \n",
+                        launch.metadata.as_ref().map_or(&launch.name, |metadata| &metadata.name),
+                        launch.metadata.as_ref().map_or(&launch.symbol, |metadata| &metadata.symbol),
+                        launch.mint,
+                        launch.outcome()
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let history_summary = match &creator_history {
+        Some(history) => format!(
+            "{} earlier launches, {} migrated, {} dev sold out{}",
+            tokens_section.len(),
+            history.outcome_count(LaunchOutcome::Migrated),
+            history.outcome_count(LaunchOutcome::Abandoned),
+            if history.complete { "" } else { " (partial history)" }
+        ),
+        None => "Unknown".to_string(),
+    };
+    let token_program = onchain_metadata
+        .as_ref()
+        .map_or("Unknown".to_string(), |metadata| metadata.token_program.to_string());
+    let creator_score = match &reputation {
+        Some(reputation) => match reputation.best_market_cap_sol {
+            Some(best) => format!("{} best launch peaked at {}", reputation, format_sol_usd(best, sol_usd)),
+            None => reputation.to_string(),
+        },
+        None => "Unknown".to_string(),
+    };
+    let mut socials: Vec<String> = [
+        ("Website", &token_data.website),
+        ("Twitter", &token_data.twitter),
+        ("Telegram", &token_data.telegram),
+    ]
+        .into_iter()
+        .filter_map(|(label, url)| url.as_ref().map(|url| format!("[{}]({})", label, url)))
+        .collect();
+    socials.extend(token_data.links.iter().map(|link| format!("[{}]({})", link.label, link.url)));
+    let socials = if socials.is_empty() { "None".to_string() } else { socials.join(" | ") };
+    let mismatches = token_data.mismatches(&create_event.name, &create_event.symbol);
+    let mut warnings = Vec::new();
+    if !mismatches.is_empty() {
+        warnings.push(format!(":warning: **Metadata mismatch:** {}", mismatches.join(", ")));
+    }
+    if let Some(clone_match) = &clone_match {
+        warnings.push(format!(":warning: **Possible clone:** {}", clone_match));
+    }
+    if let Some(logo_match) = &logo_match {
+        warnings.push(format!(
+            ":warning: **Copied logo:** same image as [{}](https://dexscreener.com/solana/{}) ({} of 64 bits differ)",
+            logo_match.mint,
+            logo_match.mint,
+            logo_match.distance
+        ));
     }
 
-    let mut fields = vec![EmbedField {
-        name: "".to_string(),
-        value: format!(
-            "**Contract Address**\n`{}`\n\n**Description**\n{}\n\n**Dev Information:**\n* Dev Holdings: `{:.2}%`\n\n**Creator Launched Tokens** \n{}",
+    // Separate fields, each within Discord's length limit, so a long
+    // description or list of links can't get the whole embed rejected
+    let mut fields = vec![
+        EmbedField::untitled(format!(
+            "**Contract Address**\n`{}`\n\n**Description**\n{}",
             create_event.mint,
-            token_data.description,
-            percentage,
-            if tokens_section.is_empty() {
-                "There is no previously launched tokens"
+            if token_data.unavailable.is_some() {
+                "_Metadata unavailable_"
             } else {
-                ""
+                &token_data.description
             }
-        ),
-        inline: true,
-    }];
-
-    fields.extend(embed_fields);
+        )),
+        EmbedField::untitled(format!("**Socials:** {}", socials)),
+    ];
+    if !warnings.is_empty() {
+        fields.push(EmbedField::untitled(warnings.join("\n\n")));
+    }
+    fields.push(EmbedField::untitled(format!(
+        "**Market Cap:** `{}`\n**Token Program:** `{}`\n\n**Dev Information:**\n* Dev Holdings: `{:.2}%`\n* Dev Buy: `{}`\n* Creator Score: `{}`",
+        market_cap,
+        token_program,
+        percentage,
+        dev_buy,
+        creator_score
+    )));
+    fields.push(EmbedField::untitled(format!(
+        "**Creator Launched Tokens** \n{}\n{}",
+        history_summary,
+        if tokens_section.is_empty() {
+            "There is no previously launched tokens"
+        } else {
+            ""
+        }
+    )));
+    // Discord also caps the embed as a whole, so only the newest launches are listed
+    let listed = tokens_section.len().saturating_sub(MAX_LISTED_LAUNCHES);
+    for chunk in tokens_section[listed..].chunks(10) {
+        fields.push(EmbedField::untitled(chunk.join("")));
+    }
+    if listed > 0 {
+        fields.push(EmbedField::untitled(format!("_and {} earlier launches_", listed)));
+    }
 
     fields.push(EmbedField {
         name: "".to_string(),
@@ -82,25 +164,68 @@ let amount=create_event.buy_event.unwrap().amount;
     });
 
     // Construct the embed object
-    let embed = Embed {
+    Embed {
         title: format!(
             "{} $({}) ",
             create_event.name,
             create_event.symbol,
         ),
         url: format!("https://dexscreener.com/solana/{}", create_event.mint),
+        description: None,
+        color: None,
         fields,
-        thumbnail: EmbedThumbnail {
-            url: token_data.image.clone(),
+        thumbnail: if token_data.image.is_empty() {
+            None
+        } else {
+            Some(EmbedThumbnail {
+                url: token_data.image.clone(),
+            })
         },
-    };
+    }
+}
 
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::utlis::{ embed::FIELD_VALUE_LIMIT, user_data::TokenLink };
+
+    fn create_event() -> CreateEvent {
+        CreateEvent {
+            name: "Moon Token".to_string(),
+            symbol: "MOON".to_string(),
+            uri: String::new(),
+            sender: Pubkey::new_unique(),
+            curve_account: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            total_supply: 1_000_000_000_000_000_000,
+            decimals: 9,
+            buy_event: None,
+        }
+    }
 
-    let message = WebhookMessage {
-        content: "".to_string(), 
-        embeds: vec![embed],
-    };
-    let webhook_url =
-    DISCORD_URL;
-    let _ = send_embed(webhook_url, message).await;
+    #[test]
+    fn long_metadata_stays_within_field_limits() {
+        let create_event = create_event();
+        let token_data = TokenMetadata {
+            name: "Another Token".to_string(),
+            symbol: "MOON".to_string(),
+            description: "To the moon! ".repeat(200),
+            links: (0..40)
+                .map(|index| TokenLink {
+                    label: format!("link {}", index),
+                    url: format!("https://moon.example/{}", index),
+                })
+                .collect(),
+            ..TokenMetadata::default()
+        };
+
+        let embed = launch_embed(&create_event, &token_data, LaunchInsights::default(), None);
+        assert!(embed.fields.iter().all(|field| field.value.chars().count() <= FIELD_VALUE_LIMIT));
+        assert!(embed.fields[0].value.contains(&create_event.mint.to_string()));
+        assert!(embed.fields[0].value.ends_with('…'));
+        assert!(embed.fields.iter().any(|field| field.value.contains("Metadata mismatch")));
+        assert!(embed.fields.iter().any(|field| field.value.contains("Dev Holdings")));
+    }
 }
@@ -0,0 +1,337 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{ atomic::{ AtomicU64, Ordering }, Arc },
+    time::{ Duration, Instant },
+};
+
+use tokio::sync::{ mpsc::{ self, error::TrySendError }, Mutex };
+
+use crate::config::PipelineConfig;
+
+type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+struct Job {
+    enqueued: Instant,
+    task: Task,
+}
+
+/// Network-bound steps of enrichment, each with its own timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Curve,
+    Metadata,
+    OnchainMetadata,
+    Image,
+    CreatorHistory,
+    Pricing,
+    Webhook,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 7] = [
+        Stage::Curve,
+        Stage::Metadata,
+        Stage::OnchainMetadata,
+        Stage::Image,
+        Stage::CreatorHistory,
+        Stage::Pricing,
+        Stage::Webhook,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Curve => "curve",
+            Stage::Metadata => "metadata",
+            Stage::OnchainMetadata => "onchain_metadata",
+            Stage::Image => "image",
+            Stage::CreatorHistory => "creator_history",
+            Stage::Pricing => "pricing",
+            Stage::Webhook => "webhook",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Default)]
+struct StageMetrics {
+    runs: AtomicU64,
+    timeouts: AtomicU64,
+    total_micros: AtomicU64,
+}
+
+/// Counters describing how well the workers keep up with ingest.
+#[derive(Default)]
+struct PipelineMetrics {
+    submitted: AtomicU64,
+    started: AtomicU64,
+    completed: AtomicU64,
+    /// Submissions dropped because the queue was full
+    dropped: AtomicU64,
+    queue_wait_micros: AtomicU64,
+    max_queue_wait_micros: AtomicU64,
+    max_depth: AtomicU64,
+    stages: [StageMetrics; Stage::ALL.len()],
+}
+
+impl PipelineMetrics {
+    fn stage(&self, stage: Stage) -> &StageMetrics {
+        &self.stages[stage.index()]
+    }
+}
+
+/// Bounded job queue drained by a fixed pool of workers. The websocket
+/// loop submits enrichment work here instead of awaiting it, so a slow
+/// fetch only occupies one worker; when every worker is busy and the queue
+/// is full, new jobs are dropped and counted rather than stalling ingest.
+#[derive(Clone)]
+pub struct Pipeline {
+    sender: mpsc::Sender<Job>,
+    capacity: usize,
+    config: Arc<PipelineConfig>,
+    metrics: Arc<PipelineMetrics>,
+}
+
+impl Pipeline {
+    /// Creates the queue and spawns `config.workers` workers on it.
+    pub fn start(config: PipelineConfig) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>(config.queue_capacity);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let metrics = Arc::new(PipelineMetrics::default());
+
+        for _ in 0..config.workers {
+            let receiver = receiver.clone();
+            let metrics = metrics.clone();
+            tokio::spawn(async move {
+                loop {
+                    let job = receiver.lock().await.recv().await;
+                    let Some(job) = job else {
+                        break;
+                    };
+                    let waited = job.enqueued.elapsed().as_micros() as u64;
+                    metrics.started.fetch_add(1, Ordering::Relaxed);
+                    metrics.queue_wait_micros.fetch_add(waited, Ordering::Relaxed);
+                    metrics.max_queue_wait_micros.fetch_max(waited, Ordering::Relaxed);
+                    job.task.await;
+                    metrics.completed.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+
+        Pipeline {
+            sender,
+            capacity: config.queue_capacity,
+            config: Arc::new(config),
+            metrics,
+        }
+    }
+
+    /// Queues `task` for a worker without waiting. Returns `false`, and
+    /// counts the drop, if the queue is full.
+    pub fn submit(&self, task: impl Future<Output = ()> + Send + 'static) -> bool {
+        let job = Job { enqueued: Instant::now(), task: Box::pin(task) };
+        self.metrics.submitted.fetch_add(1, Ordering::Relaxed);
+        let queued = match self.sender.try_send(job) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.metrics.dropped.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+            Err(TrySendError::Closed(_)) => {
+                eprintln!("Pipeline is shut down, dropping job");
+                false
+            }
+        };
+        self.metrics.max_depth.fetch_max(self.depth() as u64, Ordering::Relaxed);
+        queued
+    }
+
+    /// Runs one stage of a job under that stage's timeout. Returns `None`
+    /// (and counts it) if the timeout elapsed.
+    pub async fn stage<T>(&self, stage: Stage, future: impl Future<Output = T>) -> Option<T> {
+        let metrics = self.metrics.stage(stage);
+        let started = Instant::now();
+        let result = tokio::time::timeout(self.config.timeout(stage), future).await;
+        metrics.runs.fetch_add(1, Ordering::Relaxed);
+        metrics.total_micros.fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+        match result {
+            Ok(value) => Some(value),
+            Err(_) => {
+                metrics.timeouts.fetch_add(1, Ordering::Relaxed);
+                eprintln!("{} stage timed out after {:?}", stage.name(), self.config.timeout(stage));
+                None
+            }
+        }
+    }
+
+    /// Jobs waiting in the queue.
+    pub fn depth(&self) -> usize {
+        self.capacity - self.sender.capacity()
+    }
+
+    pub fn stats(&self) -> PipelineStats {
+        let metrics = &self.metrics;
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        PipelineStats {
+            depth: self.depth(),
+            capacity: self.capacity,
+            max_depth: load(&metrics.max_depth),
+            submitted: load(&metrics.submitted),
+            started: load(&metrics.started),
+            completed: load(&metrics.completed),
+            dropped: load(&metrics.dropped),
+            queue_wait: Duration::from_micros(load(&metrics.queue_wait_micros)),
+            max_queue_wait: Duration::from_micros(load(&metrics.max_queue_wait_micros)),
+            stages: Stage::ALL.map(|stage| {
+                let stage_metrics = metrics.stage(stage);
+                StageStats {
+                    stage,
+                    runs: load(&stage_metrics.runs),
+                    timeouts: load(&stage_metrics.timeouts),
+                    total: Duration::from_micros(load(&stage_metrics.total_micros)),
+                }
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StageStats {
+    pub stage: Stage,
+    pub runs: u64,
+    pub timeouts: u64,
+    pub total: Duration,
+}
+
+/// Snapshot of [`PipelineMetrics`].
+#[derive(Debug, Clone)]
+pub struct PipelineStats {
+    pub depth: usize,
+    pub capacity: usize,
+    pub max_depth: u64,
+    pub submitted: u64,
+    /// Jobs picked up by a worker
+    pub started: u64,
+    pub completed: u64,
+    /// Submissions dropped because the queue was full
+    pub dropped: u64,
+    /// Time jobs spent queued before a worker picked them up
+    pub queue_wait: Duration,
+    pub max_queue_wait: Duration,
+    pub stages: [StageStats; Stage::ALL.len()],
+}
+
+impl fmt::Display for PipelineStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let average = |total: Duration, count: u64| {
+            Duration::from_secs_f64(total.as_secs_f64() / (count.max(1) as f64))
+        };
+        write!(
+            f,
+            "queue {}/{} (max {}), {} submitted, {} completed, {} dropped, queue latency avg {:?} max {:?}",
+            self.depth,
+            self.capacity,
+            self.max_depth,
+            self.submitted,
+            self.completed,
+            self.dropped,
+            average(self.queue_wait, self.started),
+            self.max_queue_wait
+        )?;
+        for stage in &self.stages {
+            if stage.runs > 0 {
+                write!(
+                    f,
+                    "; {} {} runs, {} timeouts, avg {:?}",
+                    stage.stage.name(),
+                    stage.runs,
+                    stage.timeouts,
+                    average(stage.total, stage.runs)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use tokio::sync::{ oneshot, Barrier };
+
+    use super::*;
+    use crate::config::StageTimeouts;
+
+    fn pipeline(workers: usize, queue_capacity: usize) -> Pipeline {
+        Pipeline::start(PipelineConfig {
+            workers,
+            queue_capacity,
+            timeouts: StageTimeouts { curve_secs: 1, ..StageTimeouts::default() },
+        })
+    }
+
+    #[tokio::test]
+    async fn counts_stage_timeouts() {
+        let pipeline = pipeline(1, 1);
+        assert_eq!(pipeline.stage(Stage::Curve, async { 7 }).await, Some(7));
+        assert_eq!(pipeline.stage(Stage::Curve, std::future::pending::<()>()).await, None);
+
+        let stats = pipeline.stats();
+        let curve = stats.stages[Stage::Curve.index()];
+        assert_eq!((curve.runs, curve.timeouts), (2, 1));
+        assert_eq!(stats.stages[Stage::Webhook.index()].runs, 0);
+    }
+
+    #[tokio::test]
+    async fn drops_jobs_when_full_instead_of_waiting() {
+        let pipeline = pipeline(1, 1);
+        let (started_tx, started_rx) = oneshot::channel();
+        let (release_tx, release_rx) = oneshot::channel::<()>();
+        // Occupies the only worker
+        assert!(pipeline.submit(async move {
+            started_tx.send(()).unwrap();
+            release_rx.await.ok();
+        }));
+        started_rx.await.unwrap();
+        // Fills the queue
+        let ran = Arc::new(AtomicUsize::new(0));
+        let queued = ran.clone();
+        assert!(pipeline.submit(async move {
+            queued.fetch_add(1, Ordering::Relaxed);
+        }));
+        let dropped = ran.clone();
+        assert!(!pipeline.submit(async move {
+            dropped.fetch_add(1, Ordering::Relaxed);
+        }));
+        let stats = pipeline.stats();
+        assert_eq!((stats.submitted, stats.dropped, stats.depth), (3, 1, 1));
+
+        release_tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while pipeline.stats().completed < 2 {
+                tokio::task::yield_now().await;
+            }
+        }).await.unwrap();
+        assert_eq!(ran.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn workers_run_jobs_concurrently() {
+        let workers = 4;
+        let pipeline = pipeline(workers, 16);
+        // Only passes once every worker holds a job at the same time
+        let barrier = Arc::new(Barrier::new(workers + 1));
+        for _ in 0..workers {
+            let barrier = barrier.clone();
+            assert!(pipeline.submit(async move {
+                barrier.wait().await;
+            }));
+        }
+        tokio::time::timeout(Duration::from_secs(5), barrier.wait()).await.unwrap();
+    }
+}
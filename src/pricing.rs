@@ -0,0 +1,213 @@
+use std::{ sync::Mutex, time::{ Duration, Instant } };
+
+use reqwest::Client;
+
+/// Wait before retrying after the first failed refresh, doubled per further
+/// failure up to [`MAX_RETRY_DELAY`].
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(5);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Where the SOL/USD rate comes from.
+#[derive(Debug, Clone)]
+pub enum PriceSource {
+    /// `GET url` and read the rate at the JSON pointer `pointer`
+    Http {
+        url: String,
+        pointer: String,
+    },
+    /// A constant rate, for tests and offline runs
+    Fixed(f64),
+    /// Never has a rate, for testing the fallbacks
+    #[cfg(test)]
+    Failing,
+}
+
+impl PriceSource {
+    pub fn coingecko() -> Self {
+        PriceSource::Http {
+            url: "https://api.coingecko.com/api/v3/simple/price?ids=solana&vs_currencies=usd".to_string(),
+            pointer: "/solana/usd".to_string(),
+        }
+    }
+
+    async fn fetch(&self, client: &Client) -> anyhow::Result<f64> {
+        match self {
+            PriceSource::Http { url, pointer } => {
+                let body: serde_json::Value = client
+                    .get(url)
+                    .send().await?
+                    .error_for_status()?
+                    .json().await?;
+                let rate = body.pointer(pointer).and_then(|rate| {
+                    rate.as_f64().or_else(|| rate.as_str()?.parse().ok())
+                });
+                match rate {
+                    Some(rate) if rate > 0.0 => Ok(rate),
+                    _ => anyhow::bail!("no SOL/USD rate at {} in response from {}", pointer, url),
+                }
+            }
+            PriceSource::Fixed(rate) => Ok(*rate),
+            #[cfg(test)]
+            PriceSource::Failing => anyhow::bail!("no SOL/USD rate"),
+        }
+    }
+}
+
+/// SOL/USD rate cached for `ttl`. One refresh runs at a time and callers
+/// keep getting the stale rate meanwhile; a failed refresh keeps serving the
+/// last known rate rather than dropping USD values from alerts, and further
+/// refreshes back off.
+pub struct PriceService {
+    source: PriceSource,
+    ttl: Duration,
+    client: Client,
+    cache: Mutex<PriceCache>,
+    /// Held by the caller that is refreshing
+    refresh: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug, Default)]
+struct PriceCache {
+    rate: Option<(f64, Instant)>,
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+impl PriceCache {
+    /// The rate if it is fresh, or if it is stale but no refresh may start
+    /// yet; `Err` with the stale rate otherwise.
+    fn current(&self, ttl: Duration) -> Result<Option<f64>, Option<f64>> {
+        let rate = self.rate.map(|(rate, _)| rate);
+        match self.rate {
+            Some((_, fetched_at)) if fetched_at.elapsed() < ttl => Ok(rate),
+            _ if self.retry_at.is_some_and(|retry_at| Instant::now() < retry_at) => Ok(rate),
+            _ => Err(rate),
+        }
+    }
+}
+
+impl PriceService {
+    pub fn new(source: PriceSource, ttl: Duration) -> anyhow::Result<Self> {
+        let client = Client::builder().timeout(Duration::from_secs(10)).build()?;
+        Ok(Self { source, ttl, client, cache: Mutex::new(PriceCache::default()), refresh: tokio::sync::Mutex::new(()) })
+    }
+
+    pub async fn sol_usd(&self) -> Option<f64> {
+        let stale = match self.cache.lock().unwrap().current(self.ttl) {
+            Ok(rate) => {
+                return rate;
+            }
+            Err(stale) => stale,
+        };
+        // Only callers with nothing to show wait for someone else's refresh
+        let _refresh = match stale {
+            Some(rate) =>
+                match self.refresh.try_lock() {
+                    Ok(guard) => guard,
+                    Err(_) => {
+                        return Some(rate);
+                    }
+                }
+            None => self.refresh.lock().await,
+        };
+        // Refreshed (or failed) while we waited
+        if let Ok(rate) = self.cache.lock().unwrap().current(self.ttl) {
+            return rate;
+        }
+
+        let result = self.source.fetch(&self.client).await;
+        let mut cache = self.cache.lock().unwrap();
+        match result {
+            Ok(rate) => {
+                *cache = PriceCache { rate: Some((rate, Instant::now())), failures: 0, retry_at: None };
+                Some(rate)
+            }
+            Err(e) => {
+                cache.failures += 1;
+                let delay = BASE_RETRY_DELAY
+                    .saturating_mul(2u32.saturating_pow(cache.failures - 1))
+                    .min(MAX_RETRY_DELAY);
+                cache.retry_at = Some(Instant::now() + delay);
+                eprintln!("Failed to refresh SOL/USD rate, retrying in {:?}: {:#}", delay, e);
+                cache.rate.map(|(rate, _)| rate)
+            }
+        }
+    }
+}
+
+/// Formats a SOL amount with its USD value when the rate is known,
+/// e.g. `12.50 SOL ($1,875)`.
+pub fn format_sol_usd(sol: f64, sol_usd: Option<f64>) -> String {
+    match sol_usd {
+        Some(rate) => format!("{:.2} SOL (${})", sol, group_thousands((sol * rate).round() as u64)),
+        None => format!("{:.2} SOL", sol),
+    }
+}
+
+fn group_thousands(value: u64) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fetched_at(service: &PriceService) -> Option<Instant> {
+        service.cache.lock().unwrap().rate.map(|(_, fetched_at)| fetched_at)
+    }
+
+    #[tokio::test]
+    async fn serves_cached_rate_within_ttl() {
+        let service = PriceService::new(PriceSource::Fixed(150.0), Duration::from_secs(60)).unwrap();
+        assert_eq!(service.sol_usd().await, Some(150.0));
+        let first = fetched_at(&service).unwrap();
+        assert_eq!(service.sol_usd().await, Some(150.0));
+        assert_eq!(fetched_at(&service), Some(first));
+    }
+
+    #[tokio::test]
+    async fn refreshes_after_ttl() {
+        let service = PriceService::new(PriceSource::Fixed(150.0), Duration::ZERO).unwrap();
+        assert_eq!(service.sol_usd().await, Some(150.0));
+        let first = fetched_at(&service).unwrap();
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        assert_eq!(service.sol_usd().await, Some(150.0));
+        assert!(fetched_at(&service).unwrap() > first);
+    }
+
+    #[tokio::test]
+    async fn serves_stale_rate_while_backing_off() {
+        let service = PriceService::new(PriceSource::Failing, Duration::from_secs(60)).unwrap();
+        let stale_at = Instant::now() - Duration::from_secs(120);
+        service.cache.lock().unwrap().rate = Some((140.0, stale_at));
+
+        assert_eq!(service.sol_usd().await, Some(140.0));
+        assert_eq!(service.cache.lock().unwrap().failures, 1);
+        // Backing off: no second request
+        assert_eq!(service.sol_usd().await, Some(140.0));
+        assert_eq!(service.cache.lock().unwrap().failures, 1);
+        assert_eq!(fetched_at(&service), Some(stale_at));
+    }
+
+    #[tokio::test]
+    async fn no_rate_until_first_success() {
+        let service = PriceService::new(PriceSource::Failing, Duration::from_secs(60)).unwrap();
+        assert_eq!(service.sol_usd().await, None);
+        assert!(service.cache.lock().unwrap().retry_at.is_some());
+    }
+
+    #[test]
+    fn formats_usd_with_thousands_separators() {
+        assert_eq!(format_sol_usd(12.5, Some(150.0)), "12.50 SOL ($1,875)");
+        assert_eq!(format_sol_usd(10_000.0, Some(150.0)), "10000.00 SOL ($1,500,000)");
+        assert_eq!(format_sol_usd(0.5, None), "0.50 SOL");
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{ config::ReputationConfig, creators::{ CreatorHistory, CreatorLaunch, LaunchOutcome } };
+
+/// Score of a creator without earlier launches.
+const NEUTRAL_SCORE: f64 = 50.0;
+
+/// Pattern in a creator's earlier launches worth calling out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CreatorFlag {
+    /// Launched at least `serial_launcher_count` tokens before this one
+    SerialLauncher,
+    /// Sold within `fast_dump_minutes` of launching on at least half of
+    /// their launches
+    FastDumper,
+    /// Several launches, none of which migrated
+    NeverMigrated,
+    /// Earlier launches stopped trading within `short_lived_minutes`
+    ShortLived,
+}
+
+impl fmt::Display for CreatorFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CreatorFlag::SerialLauncher => "Serial launcher",
+            CreatorFlag::FastDumper => "Fast dumper",
+            CreatorFlag::NeverMigrated => "Never migrated",
+            CreatorFlag::ShortLived => "Short-lived launches",
+        })
+    }
+}
+
+/// How much a creator's track record can be trusted, from 0 (serial rugger)
+/// to 100; creators without earlier launches score 50.
+#[derive(Debug, Clone)]
+pub struct Reputation {
+    pub score: f64,
+    pub flags: Vec<CreatorFlag>,
+    /// Earlier launches the score is based on
+    pub launches: usize,
+    /// Highest market cap any earlier launch reached, in SOL
+    pub best_market_cap_sol: Option<f64>,
+}
+
+impl Reputation {
+    /// Scores the creator from their launches other than `mint`.
+    ///
+    /// Starting from 50, migrated launches add up to 50 and launches the dev
+    /// sold fast or out of subtract up to 35 and 15 (as shares of all earlier
+    /// launches); every launch beyond the third costs another 3, up to 15.
+    pub fn score(history: &CreatorHistory, mint: &Pubkey, config: &ReputationConfig) -> Self {
+        let launches: Vec<&CreatorLaunch> = history.others(mint).collect();
+        let count = launches.len();
+        let best_market_cap_sol = launches
+            .iter()
+            .filter_map(|launch| launch.peak_market_cap_sol())
+            .reduce(f64::max);
+        if count == 0 {
+            return Reputation { score: NEUTRAL_SCORE, flags: Vec::new(), launches: 0, best_market_cap_sol };
+        }
+
+        let fast_dump_secs = (config.fast_dump_minutes as i64) * 60;
+        let fast_dumped = launches
+            .iter()
+            .filter(|launch| launch.dev_first_sell_secs().is_some_and(|secs| secs <= fast_dump_secs))
+            .count();
+        let migrated = launches
+            .iter()
+            .filter(|launch| launch.outcome() == LaunchOutcome::Migrated)
+            .count();
+        let abandoned = launches
+            .iter()
+            .filter(|launch| launch.outcome() == LaunchOutcome::Abandoned)
+            .count();
+        let short_lived_secs = (config.short_lived_minutes as i64) * 60;
+        let lifetimes: Vec<i64> = launches.iter().filter_map(|launch| launch.lifetime_secs()).collect();
+        let short_lived = lifetimes
+            .iter()
+            .filter(|secs| **secs < short_lived_secs)
+            .count();
+
+        let share = |part: usize| (part as f64) / (count as f64);
+        let volume_penalty = ((count.saturating_sub(3) as f64) * 3.0).min(15.0);
+        let score =
+            NEUTRAL_SCORE + 50.0 * share(migrated) -
+            35.0 * share(fast_dumped) -
+            15.0 * share(abandoned) -
+            volume_penalty;
+
+        let mut flags = Vec::new();
+        if count >= config.serial_launcher_count {
+            flags.push(CreatorFlag::SerialLauncher);
+        }
+        if fast_dumped > 0 && fast_dumped * 2 >= count {
+            flags.push(CreatorFlag::FastDumper);
+        }
+        if count >= 3 && migrated == 0 {
+            flags.push(CreatorFlag::NeverMigrated);
+        }
+        if !lifetimes.is_empty() && short_lived * 2 > lifetimes.len() {
+            flags.push(CreatorFlag::ShortLived);
+        }
+
+        Reputation {
+            score: score.clamp(0.0, 100.0),
+            flags,
+            launches: count,
+            best_market_cap_sol,
+        }
+    }
+
+    pub fn has_flag(&self, flag: CreatorFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+}
+
+impl fmt::Display for Reputation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.0}/100", self.score)?;
+        if !self.flags.is_empty() {
+            let flags: Vec<String> = self.flags.iter().map(ToString::to_string).collect();
+            write!(f, " ({})", flags.join(", "))?;
+        }
+        Ok(())
+    }
+}
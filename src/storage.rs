@@ -0,0 +1,566 @@
+use std::{ path::Path, sync::Mutex };
+
+use rusqlite::{ params, Connection, OptionalExtension };
+
+use crate::{ event::{ EventContext, EventEnvelope, MoonEvent, TradeExecution }, utlis::user_data::TokenMetadata };
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how
+/// many have run; only ever append to this list.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE tokens (
+        mint TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        uri TEXT NOT NULL,
+        creator TEXT NOT NULL,
+        curve_account TEXT NOT NULL,
+        total_supply INTEGER NOT NULL,
+        decimals INTEGER NOT NULL,
+        signature TEXT NOT NULL UNIQUE,
+        slot INTEGER NOT NULL,
+        block_time INTEGER,
+        recorded_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+    );
+    CREATE INDEX tokens_creator ON tokens (creator);
+
+    CREATE TABLE trades (
+        signature TEXT NOT NULL,
+        instruction_index INTEGER NOT NULL,
+        mint TEXT NOT NULL,
+        trader TEXT NOT NULL,
+        side TEXT NOT NULL CHECK (side IN ('buy', 'sell')),
+        token_amount INTEGER NOT NULL,
+        collateral_amount INTEGER NOT NULL,
+        slippage_bps INTEGER NOT NULL,
+        executed_lamports INTEGER,
+        executed_tokens INTEGER,
+        price REAL,
+        slot INTEGER NOT NULL,
+        block_time INTEGER,
+        fee INTEGER NOT NULL,
+        recorded_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+        PRIMARY KEY (signature, instruction_index)
+    );
+    CREATE INDEX trades_mint_slot ON trades (mint, slot);
+    CREATE INDEX trades_trader ON trades (trader);
+
+    CREATE TABLE creators (
+        address TEXT PRIMARY KEY,
+        launch_count INTEGER NOT NULL,
+        first_seen_slot INTEGER NOT NULL,
+        last_seen_slot INTEGER NOT NULL
+    );
+
+    CREATE TABLE metadata_fetches (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        mint TEXT NOT NULL,
+        uri TEXT NOT NULL,
+        success INTEGER NOT NULL,
+        name TEXT,
+        symbol TEXT,
+        description TEXT,
+        image TEXT,
+        error TEXT,
+        fetched_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+    );
+    -- Only the latest fetch of each URI is kept
+    CREATE UNIQUE INDEX metadata_fetches_mint_uri ON metadata_fetches (mint, uri);",
+    "CREATE TABLE migrations (
+        signature TEXT NOT NULL,
+        instruction_index INTEGER NOT NULL,
+        mint TEXT NOT NULL,
+        curve_account TEXT NOT NULL,
+        dex TEXT,
+        pool TEXT,
+        sol_lamports INTEGER,
+        token_amount INTEGER,
+        slot INTEGER NOT NULL,
+        block_time INTEGER,
+        recorded_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+        PRIMARY KEY (signature, instruction_index)
+    );
+    CREATE INDEX migrations_mint ON migrations (mint);",
+    "CREATE TABLE image_hashes (
+        mint TEXT PRIMARY KEY,
+        image TEXT NOT NULL,
+        hash INTEGER NOT NULL,
+        slot INTEGER NOT NULL,
+        recorded_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+    );",
+    "CREATE TABLE creator_scans (
+        creator TEXT PRIMARY KEY,
+        newest_signature TEXT,
+        complete INTEGER NOT NULL,
+        launches TEXT NOT NULL,
+        updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+    );",
+];
+
+/// A launch as stored in the `tokens` table.
+#[derive(Debug, Clone)]
+pub struct StoredToken {
+    pub mint: String,
+    pub name: String,
+    pub symbol: String,
+    pub creator: String,
+    pub total_supply: u64,
+    pub decimals: u8,
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+}
+
+/// Summary of a mint's stored trades.
+#[derive(Debug, Clone, Copy)]
+pub struct MintActivity {
+    pub trades: u64,
+    pub peak_price: Option<f64>,
+    /// Latest trade by someone other than the creator
+    pub last_block_time: Option<i64>,
+}
+
+/// How far a creator's transaction history was scanned, see
+/// `CreatorHistoryService`. `launches` is JSON owned by that service.
+#[derive(Debug, Clone)]
+pub struct CreatorScan {
+    pub newest_signature: Option<String>,
+    pub complete: bool,
+    pub launches: String,
+}
+
+/// Embedded SQLite history of every decoded event. Writes are keyed by
+/// transaction signature, so replays (backfill, reconnects) are no-ops.
+pub struct Storage {
+    conn: Mutex<Connection>,
+}
+
+impl Storage {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&mut conn)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    pub fn open_in_memory() -> anyhow::Result<Self> {
+        let mut conn = Connection::open_in_memory()?;
+        migrate(&mut conn)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Records an event. Returns `false` if it was already stored.
+    pub fn record_event(&self, envelope: &EventEnvelope) -> anyhow::Result<bool> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let context = &envelope.context;
+
+        let inserted = match &envelope.event {
+            MoonEvent::CreateEvent(create) => {
+                let inserted =
+                    tx.execute(
+                        "INSERT OR IGNORE INTO tokens
+                        (mint, name, symbol, uri, creator, curve_account, total_supply, decimals, signature, slot, block_time)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                        params![
+                            create.mint.to_string(),
+                            create.name,
+                            create.symbol,
+                            create.uri,
+                            create.sender.to_string(),
+                            create.curve_account.to_string(),
+                            create.total_supply as i64,
+                            create.decimals,
+                            context.signature,
+                            context.slot as i64,
+                            context.block_time
+                        ]
+                    )? > 0;
+                if inserted {
+                    tx.execute(
+                        "INSERT INTO creators (address, launch_count, first_seen_slot, last_seen_slot)
+                        VALUES (?1, 1, ?2, ?2)
+                        ON CONFLICT (address) DO UPDATE SET
+                            launch_count = launch_count + 1,
+                            first_seen_slot = min(first_seen_slot, excluded.first_seen_slot),
+                            last_seen_slot = max(last_seen_slot, excluded.last_seen_slot)",
+                        params![create.sender.to_string(), context.slot as i64]
+                    )?;
+                }
+                inserted
+            }
+            MoonEvent::BuyEvent(buy) =>
+                insert_trade(
+                    &tx,
+                    context,
+                    "buy",
+                    TradeRow {
+                        mint: buy.mint.to_string(),
+                        trader: buy.sender.to_string(),
+                        token_amount: buy.amount,
+                        collateral_amount: buy.collateral_amount,
+                        slippage_bps: buy.slippage_bps,
+                        execution: buy.execution,
+                    }
+                )?,
+            MoonEvent::SellEvent(sell) =>
+                insert_trade(
+                    &tx,
+                    context,
+                    "sell",
+                    TradeRow {
+                        mint: sell.mint.to_string(),
+                        trader: sell.sender.to_string(),
+                        token_amount: sell.amount,
+                        collateral_amount: sell.collateral_amount,
+                        slippage_bps: sell.slippage_bps,
+                        execution: sell.execution,
+                    }
+                )?,
+            MoonEvent::Migration(migration) =>
+                tx.execute(
+                    "INSERT OR IGNORE INTO migrations
+                    (signature, instruction_index, mint, curve_account, dex, pool, sol_lamports, token_amount, slot, block_time)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        context.signature,
+                        context.index as i64,
+                        migration.mint.to_string(),
+                        migration.curve_account.to_string(),
+                        migration.pool.map(|(dex, _)| dex.to_string()),
+                        migration.pool.map(|(_, pool)| pool.to_string()),
+                        migration.sol_lamports.map(|lamports| lamports as i64),
+                        migration.token_amount.map(|amount| amount as i64),
+                        context.slot as i64,
+                        context.block_time
+                    ]
+                )? > 0,
+        };
+
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Records the outcome of fetching a token's off-chain metadata,
+    /// replacing any earlier fetch of the same URI.
+    pub fn record_metadata_fetch(
+        &self,
+        mint: &str,
+        uri: &str,
+        result: Result<&TokenMetadata, &str>
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        match result {
+            Ok(metadata) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO metadata_fetches (mint, uri, success, name, symbol, description, image)
+                    VALUES (?1, ?2, 1, ?3, ?4, ?5, ?6)",
+                    params![
+                        mint,
+                        uri,
+                        metadata.name,
+                        metadata.symbol,
+                        metadata.description,
+                        metadata.image
+                    ]
+                )?;
+            }
+            Err(error) => {
+                conn.execute(
+                    "INSERT OR REPLACE INTO metadata_fetches (mint, uri, success, error) VALUES (?1, ?2, 0, ?3)",
+                    params![mint, uri, error]
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Most recent launches, newest first.
+    pub fn recent_tokens(&self, limit: usize) -> anyhow::Result<Vec<StoredToken>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT mint, name, symbol, creator, total_supply, decimals, signature, slot, block_time
+            FROM tokens ORDER BY slot DESC LIMIT ?1"
+        )?;
+        let tokens = statement
+            .query_map(params![limit as i64], stored_token)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tokens)
+    }
+
+    /// Whether a migration of `mint` was recorded.
+    pub fn migrated(&self, mint: &str) -> anyhow::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        Ok(
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM migrations WHERE mint = ?1)",
+                params![mint],
+                |row| row.get(0)
+            )?
+        )
+    }
+
+    /// Trade count and highest price of `mint`, and the latest trade by
+    /// anyone other than `creator`, if it has stored trades.
+    pub fn mint_activity(&self, mint: &str, creator: &str) -> anyhow::Result<Option<MintActivity>> {
+        let conn = self.conn.lock().unwrap();
+        let activity = conn.query_row(
+            "SELECT COUNT(*), MAX(price), MAX(CASE WHEN trader != ?2 THEN block_time END)
+            FROM trades WHERE mint = ?1",
+            params![mint, creator],
+            |row| {
+                Ok(MintActivity {
+                    trades: row.get::<_, i64>(0)? as u64,
+                    peak_price: row.get(1)?,
+                    last_block_time: row.get(2)?,
+                })
+            }
+        )?;
+        Ok(Some(activity).filter(|activity| activity.trades > 0))
+    }
+
+    /// Stores the perceptual hash of `mint`'s image.
+    pub fn record_image_hash(&self, mint: &str, image: &str, hash: u64, slot: u64) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO image_hashes (mint, image, hash, slot) VALUES (?1, ?2, ?3, ?4)",
+            params![mint, image, hash as i64, slot as i64]
+        )?;
+        Ok(())
+    }
+
+    pub fn save_creator_scan(&self, creator: &str, scan: &CreatorScan) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO creator_scans (creator, newest_signature, complete, launches)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![creator, scan.newest_signature, scan.complete, scan.launches]
+        )?;
+        Ok(())
+    }
+
+    pub fn creator_scan(&self, creator: &str) -> anyhow::Result<Option<CreatorScan>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(
+            conn
+                .query_row(
+                    "SELECT newest_signature, complete, launches FROM creator_scans WHERE creator = ?1",
+                    params![creator],
+                    |row| {
+                        Ok(CreatorScan {
+                            newest_signature: row.get(0)?,
+                            complete: row.get(1)?,
+                            launches: row.get(2)?,
+                        })
+                    }
+                )
+                .optional()?
+        )
+    }
+
+    /// Image hashes of the latest `limit` launches as `(mint, hash)`,
+    /// newest first.
+    pub fn image_hashes(&self, limit: usize) -> anyhow::Result<Vec<(String, u64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare("SELECT mint, hash FROM image_hashes ORDER BY slot DESC LIMIT ?1")?;
+        let hashes = statement
+            .query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(hashes)
+    }
+
+    pub fn token(&self, mint: &str) -> anyhow::Result<Option<StoredToken>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(
+            conn
+                .query_row(
+                    "SELECT mint, name, symbol, creator, total_supply, decimals, signature, slot, block_time
+                    FROM tokens WHERE mint = ?1",
+                    params![mint],
+                    stored_token
+                )
+                .optional()?
+        )
+    }
+}
+
+fn stored_token(row: &rusqlite::Row) -> rusqlite::Result<StoredToken> {
+    Ok(StoredToken {
+        mint: row.get(0)?,
+        name: row.get(1)?,
+        symbol: row.get(2)?,
+        creator: row.get(3)?,
+        total_supply: row.get::<_, i64>(4)? as u64,
+        decimals: row.get(5)?,
+        signature: row.get(6)?,
+        slot: row.get::<_, i64>(7)? as u64,
+        block_time: row.get(8)?,
+    })
+}
+
+struct TradeRow {
+    mint: String,
+    trader: String,
+    token_amount: u64,
+    collateral_amount: u64,
+    slippage_bps: u64,
+    execution: Option<TradeExecution>,
+}
+
+fn insert_trade(
+    tx: &rusqlite::Transaction,
+    context: &EventContext,
+    side: &str,
+    trade: TradeRow
+) -> anyhow::Result<bool> {
+    let inserted = tx.execute(
+        "INSERT OR IGNORE INTO trades
+        (signature, instruction_index, mint, trader, side, token_amount, collateral_amount, slippage_bps,
+         executed_lamports, executed_tokens, price, slot, block_time, fee)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![
+            context.signature,
+            context.index as i64,
+            trade.mint,
+            trade.trader,
+            side,
+            trade.token_amount as i64,
+            trade.collateral_amount as i64,
+            trade.slippage_bps as i64,
+            trade.execution.map(|execution| execution.sol_lamports as i64),
+            trade.execution.map(|execution| execution.token_amount as i64),
+            trade.execution.map(|execution| execution.price),
+            context.slot as i64,
+            context.block_time,
+            context.fee as i64
+        ]
+    )?;
+    Ok(inserted > 0)
+}
+
+fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::event::{ BuyEvent, EventContext, SellEvent };
+
+    fn envelope(signature: &str, slot: u64, event: MoonEvent) -> EventEnvelope {
+        EventEnvelope {
+            context: EventContext {
+                signature: signature.to_string(),
+                slot,
+                block_time: Some(1_720_000_000 + (slot as i64)),
+                fee_payer: Pubkey::default(),
+                fee: 5000,
+                compute_units: 0,
+                success: true,
+                index: 0,
+            },
+            event,
+        }
+    }
+
+    fn buy(mint: Pubkey, sender: Pubkey) -> MoonEvent {
+        MoonEvent::BuyEvent(BuyEvent {
+            amount: 1_000_000,
+            collateral_amount: 1_000,
+            slippage_bps: 100,
+            sender,
+            curve_account: Pubkey::new_unique(),
+            mint,
+            execution: None,
+        })
+    }
+
+    fn sell(mint: Pubkey, sender: Pubkey) -> MoonEvent {
+        MoonEvent::SellEvent(SellEvent {
+            amount: 1_000_000,
+            collateral_amount: 900,
+            slippage_bps: 100,
+            sender,
+            curve_account: Pubkey::new_unique(),
+            mint,
+            execution: None,
+        })
+    }
+
+    fn metadata_fetches(storage: &Storage, mint: &str) -> Vec<(String, bool)> {
+        let conn = storage.conn.lock().unwrap();
+        let mut statement = conn.prepare("SELECT uri, success FROM metadata_fetches WHERE mint = ?1 ORDER BY uri").unwrap();
+        statement
+            .query_map(params![mint], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn replayed_events_are_stored_once() {
+        let storage = Storage::open_in_memory().unwrap();
+        let event = envelope("sig-1", 10, buy(Pubkey::new_unique(), Pubkey::new_unique()));
+        assert!(storage.record_event(&event).unwrap());
+        assert!(!storage.record_event(&event).unwrap());
+    }
+
+    #[test]
+    fn keeps_latest_metadata_fetch_per_uri() {
+        let storage = Storage::open_in_memory().unwrap();
+        let metadata = TokenMetadata { name: "Token".to_string(), ..TokenMetadata::default() };
+        storage.record_metadata_fetch("mint", "ipfs://a", Err("timed out")).unwrap();
+        storage.record_metadata_fetch("mint", "ipfs://a", Ok(&metadata)).unwrap();
+        storage.record_metadata_fetch("mint", "ipfs://b", Err("not found")).unwrap();
+
+        let fetches = metadata_fetches(&storage, "mint");
+        assert_eq!(fetches, vec![("ipfs://a".to_string(), true), ("ipfs://b".to_string(), false)]);
+    }
+
+    #[test]
+    fn mint_activity_ignores_creator_for_last_trade() {
+        let storage = Storage::open_in_memory().unwrap();
+        let mint = Pubkey::new_unique();
+        let creator = Pubkey::new_unique();
+        let trader = Pubkey::new_unique();
+        storage.record_event(&envelope("sig-1", 10, buy(mint, creator))).unwrap();
+        storage.record_event(&envelope("sig-2", 20, buy(mint, trader))).unwrap();
+        storage.record_event(&envelope("sig-3", 30, sell(mint, creator))).unwrap();
+
+        let activity = storage.mint_activity(&mint.to_string(), &creator.to_string()).unwrap().unwrap();
+        assert_eq!(activity.trades, 3);
+        assert_eq!(activity.last_block_time, Some(1_720_000_020));
+        assert!(storage.mint_activity(&Pubkey::new_unique().to_string(), &creator.to_string()).unwrap().is_none());
+    }
+
+    #[test]
+    fn creator_scan_round_trips() {
+        let storage = Storage::open_in_memory().unwrap();
+        assert!(storage.creator_scan("creator").unwrap().is_none());
+        let scan = CreatorScan { newest_signature: Some("sig".to_string()), complete: false, launches: "[]".to_string() };
+        storage.save_creator_scan("creator", &scan).unwrap();
+        storage.save_creator_scan("creator", &CreatorScan { complete: true, ..scan }).unwrap();
+
+        let stored = storage.creator_scan("creator").unwrap().unwrap();
+        assert_eq!(stored.newest_signature.as_deref(), Some("sig"));
+        assert!(stored.complete);
+    }
+
+    #[test]
+    fn image_hashes_are_newest_first() {
+        let storage = Storage::open_in_memory().unwrap();
+        storage.record_image_hash("old", "a", 1, 10).unwrap();
+        storage.record_image_hash("new", "b", u64::MAX, 20).unwrap();
+
+        assert_eq!(storage.image_hashes(1).unwrap(), vec![("new".to_string(), u64::MAX)]);
+        assert_eq!(storage.image_hashes(5).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,278 @@
+use std::{ collections::HashMap, fmt, sync::Mutex };
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::event::{ EventEnvelope, MoonEvent };
+
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+/// One trader's running totals in one mint.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Position {
+    pub tokens_bought: u64,
+    pub tokens_sold: u64,
+    pub lamports_spent: u64,
+    pub lamports_received: u64,
+    pub trades: u64,
+}
+
+impl Position {
+    /// Tokens held from observed trades (raw amount, never negative).
+    pub fn holdings(&self) -> u64 {
+        self.tokens_bought.saturating_sub(self.tokens_sold)
+    }
+
+    /// SOL received minus SOL spent.
+    pub fn realized_sol(&self) -> f64 {
+        ((self.lamports_received as f64) - (self.lamports_spent as f64)) / LAMPORTS_PER_SOL
+    }
+}
+
+/// Aggregated trade flow of one mint since the monitor started watching it.
+#[derive(Debug, Clone, Default)]
+pub struct MintStats {
+    pub buys: u64,
+    pub sells: u64,
+    pub buy_lamports: u64,
+    pub sell_lamports: u64,
+    pub positions: HashMap<Pubkey, Position>,
+    pub first_slot: u64,
+    pub last_slot: u64,
+    /// SOL per whole token of the most recent trade
+    pub last_price: Option<f64>,
+}
+
+impl MintStats {
+    pub fn trade_count(&self) -> u64 {
+        self.buys + self.sells
+    }
+
+    pub fn unique_traders(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn volume_sol(&self) -> f64 {
+        ((self.buy_lamports + self.sell_lamports) as f64) / LAMPORTS_PER_SOL
+    }
+
+    /// SOL that flowed into the curve (buys) minus SOL that flowed out (sells).
+    pub fn net_flow_sol(&self) -> f64 {
+        ((self.buy_lamports as f64) - (self.sell_lamports as f64)) / LAMPORTS_PER_SOL
+    }
+
+    pub fn position(&self, trader: &Pubkey) -> Position {
+        self.positions.get(trader).copied().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for MintStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_totals(f, self.buys, self.sells, self.unique_traders(), self.volume_sol(), self.net_flow_sol())
+    }
+}
+
+/// The one-line summary shared by [`MintStats`] and [`TradeUpdate`].
+fn write_totals(
+    f: &mut fmt::Formatter,
+    buys: u64,
+    sells: u64,
+    unique_traders: usize,
+    volume_sol: f64,
+    net_flow_sol: f64
+) -> fmt::Result {
+    write!(
+        f,
+        "{} trades ({} buys / {} sells) by {} traders, volume {:.2} SOL, net flow {:+.2} SOL",
+        buys + sells,
+        buys,
+        sells,
+        unique_traders,
+        volume_sol,
+        net_flow_sol
+    )
+}
+
+/// A mint's totals right after one trade, and the trading wallet's position
+/// including it; what [`TradeAggregator::record`] reports.
+#[derive(Debug, Clone, Copy)]
+pub struct TradeUpdate {
+    pub position: Position,
+    pub buys: u64,
+    pub sells: u64,
+    pub unique_traders: usize,
+    pub volume_sol: f64,
+    pub net_flow_sol: f64,
+}
+
+impl fmt::Display for TradeUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_totals(f, self.buys, self.sells, self.unique_traders, self.volume_sol, self.net_flow_sol)
+    }
+}
+
+/// A buy or sell reduced to what the aggregates need.
+#[derive(Debug, Clone, Copy)]
+pub struct Trade {
+    pub mint: Pubkey,
+    pub trader: Pubkey,
+    pub is_buy: bool,
+    /// Executed amounts when known, otherwise the requested ones
+    pub token_amount: u64,
+    pub lamports: u64,
+    pub price: Option<f64>,
+    pub slot: u64,
+}
+
+impl Trade {
+    pub fn from_envelope(envelope: &EventEnvelope) -> Option<Self> {
+        let (mint, trader, is_buy, amount, collateral_amount, execution) = match &envelope.event {
+            MoonEvent::BuyEvent(buy) =>
+                (buy.mint, buy.sender, true, buy.amount, buy.collateral_amount, buy.execution),
+            MoonEvent::SellEvent(sell) =>
+                (sell.mint, sell.sender, false, sell.amount, sell.collateral_amount, sell.execution),
+            MoonEvent::CreateEvent(_) | MoonEvent::Migration(_) => {
+                return None;
+            }
+        };
+        Some(Trade {
+            mint,
+            trader,
+            is_buy,
+            token_amount: execution.map_or(amount, |execution| execution.token_amount),
+            lamports: execution.map_or(collateral_amount, |execution| execution.sol_lamports),
+            price: execution.filter(|execution| execution.token_amount > 0).map(|execution| execution.price),
+            slot: envelope.context.slot,
+        })
+    }
+
+    pub fn sol(&self) -> f64 {
+        (self.lamports as f64) / LAMPORTS_PER_SOL
+    }
+}
+
+/// Per-mint trade aggregates shared between the event loop, alerts and embeds.
+#[derive(Default)]
+pub struct TradeAggregator {
+    stats: Mutex<HashMap<Pubkey, MintStats>>,
+}
+
+impl TradeAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a buy or sell to its mint's aggregates and returns the updated
+    /// totals with the trader's position; creates are ignored.
+    pub fn record(&self, envelope: &EventEnvelope) -> Option<TradeUpdate> {
+        let trade = Trade::from_envelope(envelope)?;
+
+        let mut stats = self.stats.lock().unwrap();
+        let mint = stats.entry(trade.mint).or_insert_with(|| MintStats {
+            first_slot: trade.slot,
+            ..MintStats::default()
+        });
+        let position = mint.positions.entry(trade.trader).or_default();
+        position.trades += 1;
+        if trade.is_buy {
+            mint.buys += 1;
+            mint.buy_lamports += trade.lamports;
+            position.tokens_bought += trade.token_amount;
+            position.lamports_spent += trade.lamports;
+        } else {
+            mint.sells += 1;
+            mint.sell_lamports += trade.lamports;
+            position.tokens_sold += trade.token_amount;
+            position.lamports_received += trade.lamports;
+        }
+        if trade.slot >= mint.last_slot {
+            mint.last_slot = trade.slot;
+            mint.last_price = trade.price.or(mint.last_price);
+        }
+        mint.first_slot = mint.first_slot.min(trade.slot);
+        Some(TradeUpdate {
+            position: mint.position(&trade.trader),
+            buys: mint.buys,
+            sells: mint.sells,
+            unique_traders: mint.unique_traders(),
+            volume_sol: mint.volume_sol(),
+            net_flow_sol: mint.net_flow_sol(),
+        })
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<MintStats> {
+        self.stats.lock().unwrap().get(mint).cloned()
+    }
+
+    /// Mints with the highest volume, for summaries.
+    pub fn top_by_volume(&self, limit: usize) -> Vec<(Pubkey, MintStats)> {
+        let stats = self.stats.lock().unwrap();
+        let mut top: Vec<_> = stats.iter().map(|(mint, stats)| (*mint, stats.clone())).collect();
+        top.sort_by(|a, b| b.1.volume_sol().total_cmp(&a.1.volume_sol()));
+        top.truncate(limit);
+        top
+    }
+
+    /// Forgets mints without trades since `min_slot`, so memory stays bounded.
+    pub fn prune(&self, min_slot: u64) {
+        self.stats.lock().unwrap().retain(|_, stats| stats.last_slot >= min_slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{ BuyEvent, EventContext, SellEvent };
+
+    fn envelope(slot: u64, event: MoonEvent) -> EventEnvelope {
+        EventEnvelope {
+            context: EventContext {
+                signature: format!("sig-{}", slot),
+                slot,
+                block_time: None,
+                fee_payer: Pubkey::default(),
+                fee: 5000,
+                compute_units: 0,
+                success: true,
+                index: 0,
+            },
+            event,
+        }
+    }
+
+    #[test]
+    fn record_reports_totals_and_the_traders_position() {
+        let aggregator = TradeAggregator::new();
+        let mint = Pubkey::new_unique();
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let buy = |sender, amount, collateral_amount| {
+            MoonEvent::BuyEvent(BuyEvent {
+                amount,
+                collateral_amount,
+                slippage_bps: 100,
+                sender,
+                curve_account: Pubkey::default(),
+                mint,
+                execution: None,
+            })
+        };
+        aggregator.record(&envelope(1, buy(alice, 1_000, 2_000_000_000)));
+        aggregator.record(&envelope(2, buy(bob, 500, 1_000_000_000)));
+        let sell = MoonEvent::SellEvent(SellEvent {
+            amount: 400,
+            collateral_amount: 1_000_000_000,
+            slippage_bps: 100,
+            sender: alice,
+            curve_account: Pubkey::default(),
+            mint,
+            execution: None,
+        });
+        let update = aggregator.record(&envelope(3, sell)).unwrap();
+
+        assert_eq!((update.buys, update.sells, update.unique_traders), (2, 1, 2));
+        assert_eq!(update.volume_sol, 4.0);
+        assert_eq!(update.net_flow_sol, 2.0);
+        assert_eq!(update.position.holdings(), 600);
+        assert_eq!(update.position.trades, 2);
+        assert_eq!(update.to_string(), aggregator.get(&mint).unwrap().to_string());
+    }
+}
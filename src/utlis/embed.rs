@@ -1,5 +1,8 @@
+use std::{ collections::HashMap, fmt, sync::Mutex, time::{ Duration, Instant } };
+
 use serde::Serialize;
-use reqwest::Client;
+use serde_json::Value;
+use reqwest::{ header::HeaderMap, Client, StatusCode };
 
 #[derive(Debug, Serialize)]
 pub struct EmbedField {
@@ -8,12 +11,38 @@ pub struct EmbedField {
     pub inline: bool,
 }
 
+/// Most characters Discord accepts in a field value.
+pub const FIELD_VALUE_LIMIT: usize = 1024;
+
+impl EmbedField {
+    /// Unnamed, full-width field; `value` is cut to [`FIELD_VALUE_LIMIT`] so
+    /// one long piece of user-supplied text can't get the embed rejected.
+    pub fn untitled(value: String) -> Self {
+        Self { name: String::new(), value: truncate(value, FIELD_VALUE_LIMIT), inline: false }
+    }
+}
+
+/// `text` cut to at most `limit` characters, ending in an ellipsis if cut.
+pub fn truncate(text: String, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text;
+    }
+    let mut cut: String = text.chars().take(limit.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
 #[derive(Debug, Serialize)]
 pub struct Embed {
     pub title: String,
     pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<u32>,
     pub fields: Vec<EmbedField>,
-    pub thumbnail: EmbedThumbnail,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<EmbedThumbnail>,
 }
 
 #[derive(Debug, Serialize)]
@@ -27,18 +56,265 @@ pub struct WebhookMessage {
     pub embeds: Vec<Embed>,
 }
 
-pub async fn send_embed(webhook_url: &str, message: WebhookMessage) -> Result<(), reqwest::Error> {
-    let client = Client::new();
-    let res = client.post(webhook_url).json(&message).send().await.unwrap();
+/// Attempts per message before it is dropped.
+const MAX_ATTEMPTS: u32 = 4;
+/// Backoff before the first retry of a failed request, doubled each retry.
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Why a webhook message was dropped.
+#[derive(Debug)]
+pub enum WebhookError {
+    /// The request itself failed on every attempt, e.g. connection errors
+    Request(reqwest::Error),
+    /// Still rate limited after the last attempt, or the wait would run
+    /// past the send deadline
+    RateLimited { retry_after: Duration },
+    /// Discord answered with a 5xx on every attempt
+    Server { status: StatusCode, attempts: u32 },
+    /// Discord refused the message (bad payload, deleted webhook, ...);
+    /// not retried
+    Rejected { status: StatusCode, body: String },
+}
+
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "request failed: {}", e),
+            Self::RateLimited { retry_after } => write!(f, "rate limited, retry after {:?}", retry_after),
+            Self::Server { status, attempts } => write!(f, "server error {} after {} attempts", status, attempts),
+            Self::Rejected { status, body } => write!(f, "rejected with {}: {}", status, body),
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Discord's rate limit state for one webhook, from its `X-RateLimit-*`
+/// headers.
+#[derive(Debug, Default)]
+struct Bucket {
+    remaining: Option<u64>,
+    reset_at: Option<Instant>,
+}
+
+/// Posts webhook messages over one pooled connection, waiting out Discord's
+/// per-webhook and global rate limits and retrying transient failures.
+pub struct WebhookClient {
+    client: Client,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    /// Set by a global 429; every webhook waits until then
+    global_reset_at: Mutex<Option<Instant>>,
+    /// Time one [`WebhookClient::send`] may take, waits and retries included
+    send_timeout: Duration,
+}
+
+impl WebhookClient {
+    pub fn new(send_timeout: Duration) -> anyhow::Result<Self> {
+        let client = Client::builder().timeout(Duration::from_secs(10)).build()?;
+        Ok(Self { client, buckets: Mutex::new(HashMap::new()), global_reset_at: Mutex::new(None), send_timeout })
+    }
+
+    /// Posts `message`, giving up once waiting for a rate limit or a retry
+    /// would run past `send_timeout`.
+    pub async fn send(&self, webhook_url: &str, message: &WebhookMessage) -> Result<(), WebhookError> {
+        let deadline = Instant::now() + self.send_timeout;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            loop {
+                let wait = self.reserve(webhook_url);
+                if wait.is_zero() {
+                    break;
+                }
+                if Instant::now() + wait > deadline {
+                    return Err(WebhookError::RateLimited { retry_after: wait });
+                }
+                tokio::time::sleep(wait).await;
+            }
+
+            let response = match self.client.post(webhook_url).json(message).send().await {
+                Ok(response) => response,
+                Err(e) if
+                    attempt < MAX_ATTEMPTS &&
+                    (e.is_connect() || e.is_timeout() || e.is_request()) &&
+                    Instant::now() + backoff(attempt) <= deadline
+                => {
+                    eprintln!("Webhook request failed ({}), retrying", e);
+                    tokio::time::sleep(backoff(attempt)).await;
+                    continue;
+                }
+                Err(e) => {
+                    return Err(WebhookError::Request(e));
+                }
+            };
+            self.update_bucket(webhook_url, response.headers());
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(());
+            }
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                let headers = response.headers().clone();
+                let body: Value = response.json().await.unwrap_or_default();
+                let retry_after = retry_after(&headers, &body);
+                let global =
+                    header(&headers, "x-ratelimit-global").is_some_and(|value| value == "true") ||
+                    body["global"].as_bool().unwrap_or(false);
+                let reset_at = Instant::now() + retry_after;
+                if global {
+                    *self.global_reset_at.lock().unwrap() = Some(reset_at);
+                } else {
+                    let mut buckets = self.buckets.lock().unwrap();
+                    let bucket = buckets.entry(webhook_url.to_string()).or_default();
+                    bucket.remaining = Some(0);
+                    bucket.reset_at = Some(reset_at);
+                }
+                if attempt >= MAX_ATTEMPTS {
+                    return Err(WebhookError::RateLimited { retry_after });
+                }
+                eprintln!("Webhook rate limited{}, retrying in {:?}", if global { " globally" } else { "" }, retry_after);
+                continue;
+            }
+            if status.is_server_error() {
+                if attempt >= MAX_ATTEMPTS || Instant::now() + backoff(attempt) > deadline {
+                    return Err(WebhookError::Server { status, attempts: attempt });
+                }
+                eprintln!("Webhook returned {}, retrying", status);
+                tokio::time::sleep(backoff(attempt)).await;
+                continue;
+            }
+            let body = response.text().await.unwrap_or_default();
+            return Err(WebhookError::Rejected { status, body });
+        }
+    }
+
+    /// How long to wait before the next request to `webhook_url` may go out.
+    /// When it may go out now, takes one request off the bucket so that
+    /// concurrent sends don't all spend the last one.
+    fn reserve(&self, webhook_url: &str) -> Duration {
+        let now = Instant::now();
+        let global = self.global_reset_at
+            .lock()
+            .unwrap()
+            .map(|reset_at| reset_at.saturating_duration_since(now))
+            .unwrap_or_default();
+        if !global.is_zero() {
+            return global;
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        let Some(bucket) = buckets.get_mut(webhook_url) else {
+            return Duration::ZERO;
+        };
+        match (bucket.remaining, bucket.reset_at) {
+            // The window is over; the next response tells us the new one
+            (_, Some(reset_at)) if reset_at <= now => {
+                *bucket = Bucket::default();
+                Duration::ZERO
+            }
+            (Some(0), Some(reset_at)) => reset_at - now,
+            (Some(remaining), _) => {
+                bucket.remaining = Some(remaining - 1);
+                Duration::ZERO
+            }
+            (None, _) => Duration::ZERO,
+        }
+    }
+
+    fn update_bucket(&self, webhook_url: &str, headers: &HeaderMap) {
+        let remaining = header(headers, "x-ratelimit-remaining").and_then(|value| value.parse().ok());
+        let reset_after = header(headers, "x-ratelimit-reset-after").and_then(seconds);
+        if remaining.is_none() && reset_after.is_none() {
+            return;
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(webhook_url.to_string()).or_default();
+        bucket.remaining = remaining;
+        bucket.reset_at = reset_after.map(|reset_after| Instant::now() + reset_after);
+    }
+}
+
+/// Wait requested by a 429: the `Retry-After` header, else the body's
+/// `retry_after`, else one second.
+fn retry_after(headers: &HeaderMap, body: &Value) -> Duration {
+    header(headers, "retry-after")
+        .and_then(seconds)
+        .or_else(|| body["retry_after"].as_f64().and_then(|secs| Duration::try_from_secs_f64(secs).ok()))
+        .unwrap_or(Duration::from_secs(1))
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok()
+}
 
-    if res.status().is_success() {
-        println!("Message sent successfully");
-    } else {
-        let status = res.status();
-        let body = res.text().await?;
-        println!("{:?}", message);
-        println!("Failed to send message: Status: {:?}, Body: {:?}", status, body);
+/// Parses a header holding (possibly fractional) seconds.
+fn seconds(value: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(value.trim().parse().ok()?).ok()
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF * 2u32.pow(attempt.saturating_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+    use serde_json::json;
+
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn seconds_parses_fractional_seconds() {
+        assert_eq!(seconds("2"), Some(Duration::from_secs(2)));
+        assert_eq!(seconds(" 0.25 "), Some(Duration::from_millis(250)));
+        assert_eq!(seconds("1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(seconds("soon"), None);
+        assert_eq!(seconds("-1"), None);
+        assert_eq!(seconds(""), None);
+    }
+
+    #[test]
+    fn retry_after_prefers_header_then_body() {
+        let body = json!({ "retry_after": 0.75, "global": false });
+        assert_eq!(retry_after(&headers(&[("retry-after", "3")]), &body), Duration::from_secs(3));
+        assert_eq!(retry_after(&HeaderMap::new(), &body), Duration::from_millis(750));
+        assert_eq!(retry_after(&headers(&[("retry-after", "later")]), &body), Duration::from_millis(750));
+        assert_eq!(retry_after(&HeaderMap::new(), &Value::Null), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn reserve_spends_remaining_requests() {
+        let client = WebhookClient::new(Duration::from_secs(5)).unwrap();
+        let url = "https://discord.com/api/webhooks/1/token";
+        client.update_bucket(url, &headers(&[("x-ratelimit-remaining", "2"), ("x-ratelimit-reset-after", "60")]));
+
+        assert!(client.reserve(url).is_zero());
+        assert!(client.reserve(url).is_zero());
+        let wait = client.reserve(url);
+        assert!(wait > Duration::from_secs(59) && wait <= Duration::from_secs(60), "{:?}", wait);
     }
 
-    Ok(())
+    #[test]
+    fn reserve_forgets_expired_window() {
+        let client = WebhookClient::new(Duration::from_secs(5)).unwrap();
+        let url = "https://discord.com/api/webhooks/1/token";
+        client.update_bucket(url, &headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset-after", "0")]));
+
+        assert!(client.reserve(url).is_zero());
+        assert!(client.reserve(url).is_zero());
+    }
 }
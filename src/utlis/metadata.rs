@@ -0,0 +1,298 @@
+use std::{ collections::{ HashMap, VecDeque }, sync::Mutex, time::{ Duration, Instant } };
+
+use futures_util::{ stream::FuturesUnordered, StreamExt };
+use reqwest::Client;
+use url::Url;
+
+use crate::{ config::MetadataConfig, utlis::user_data::TokenMetadata };
+
+/// Resolves off-chain token metadata JSON. IPFS and Arweave URIs (and URLs
+/// on any gateway) are tried across the configured gateways with hedged
+/// requests: the next candidate starts when the current one fails or is
+/// slower than `hedge_delay_ms`, and the first valid response wins. Results are
+/// cached by URI, failures for `failure_ttl_secs`.
+pub struct MetadataResolver {
+    client: Client,
+    config: MetadataConfig,
+    cache: Mutex<MetadataCache>,
+}
+
+#[derive(Default)]
+struct MetadataCache {
+    entries: HashMap<String, TokenMetadata>,
+    order: VecDeque<String>,
+    /// Why each recently failed URI failed, and until when that stands
+    failures: HashMap<String, (String, Instant)>,
+}
+
+impl MetadataResolver {
+    pub fn new(config: MetadataConfig) -> anyhow::Result<Self> {
+        let client = Client::builder().timeout(Duration::from_secs(config.request_timeout_secs)).build()?;
+        Ok(Self { client, config, cache: Mutex::new(MetadataCache::default()) })
+    }
+
+    /// Metadata behind `uri`. Never fails: if no candidate URL yields valid
+    /// JSON, the result is [`TokenMetadata::unavailable`] with `name` and
+    /// `symbol` filled in from the caller.
+    pub async fn resolve(&self, uri: &str, name: &str, symbol: &str) -> TokenMetadata {
+        {
+            let cache = self.cache.lock().unwrap();
+            if let Some(metadata) = cache.entries.get(uri) {
+                return metadata.clone();
+            }
+            if let Some((reason, _)) = cache.failures.get(uri).filter(|(_, until)| Instant::now() < *until) {
+                return TokenMetadata::unavailable(name, symbol, reason.clone());
+            }
+        }
+
+        let parse = |body: Vec<u8>| Ok(serde_json::from_slice::<TokenMetadata>(&body)?);
+        match self.fetch(uri, self.config.max_bytes, parse).await {
+            Ok(metadata) => {
+                self.remember(uri, &metadata);
+                metadata
+            }
+            Err(e) => {
+                eprintln!("Metadata unavailable for {}: {:#}", uri, e);
+                let reason = format!("{:#}", e);
+                self.remember_failure(uri, &reason);
+                TokenMetadata::unavailable(name, symbol, reason)
+            }
+        }
+    }
+
+    /// Downloads the file behind `uri` (e.g. a token image) through the same
+    /// gateways, rejecting files larger than `max_bytes`. Not cached.
+    pub async fn fetch_bytes(&self, uri: &str, max_bytes: usize) -> anyhow::Result<Vec<u8>> {
+        self.fetch(uri, max_bytes, Ok).await
+    }
+
+    /// Tries the candidates for `uri` until one returns a body `parse`
+    /// accepts.
+    async fn fetch<T>(
+        &self,
+        uri: &str,
+        max_bytes: usize,
+        parse: impl Fn(Vec<u8>) -> anyhow::Result<T>
+    ) -> anyhow::Result<T> {
+        let candidates = self.candidates(uri);
+        if candidates.is_empty() {
+            anyhow::bail!("unsupported metadata uri {:?}", uri);
+        }
+
+        let mut pending = candidates.iter();
+        let mut in_flight = FuturesUnordered::new();
+        let mut last_error = None;
+        loop {
+            if let Some(url) = pending.next() {
+                in_flight.push(async { parse(self.fetch_url(url, max_bytes).await?) });
+            }
+            if in_flight.is_empty() {
+                break;
+            }
+            let hedge = tokio::time::sleep(Duration::from_millis(self.config.hedge_delay_ms));
+            tokio::select! {
+                Some(result) = in_flight.next() => match result {
+                    Ok(value) => return Ok(value),
+                    Err(e) => last_error = Some(e),
+                },
+                // Slow response: start the next candidate alongside it
+                _ = hedge, if pending.len() > 0 => {}
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("no gateway answered")))
+    }
+
+    async fn fetch_url(&self, url: &str, limit: usize) -> anyhow::Result<Vec<u8>> {
+        let mut response = self.client.get(url).send().await?.error_for_status()?;
+        if response.content_length().is_some_and(|len| len > (limit as u64)) {
+            anyhow::bail!("{} is larger than {} bytes", url, limit);
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > limit {
+                anyhow::bail!("{} is larger than {} bytes", url, limit);
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    /// URLs to try for `uri`, the original first when it is one.
+    fn candidates(&self, uri: &str) -> Vec<String> {
+        let uri = uri.trim();
+        let mut candidates = Vec::new();
+        if let Ok(url) = Url::parse(uri) {
+            if url.scheme() == "http" || url.scheme() == "https" {
+                candidates.push(uri.to_string());
+            }
+        }
+
+        let (gateways, path) = match content_path(uri) {
+            Some(ContentPath::Ipfs(path)) => (&self.config.ipfs_gateways, path),
+            Some(ContentPath::Arweave(path)) => (&self.config.arweave_gateways, path),
+            None => {
+                return candidates;
+            }
+        };
+        for gateway in gateways {
+            let url = join(gateway, &path);
+            if !candidates.contains(&url) {
+                candidates.push(url);
+            }
+        }
+        candidates
+    }
+
+    fn remember(&self, uri: &str, metadata: &TokenMetadata) {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.entries.insert(uri.to_string(), metadata.clone()).is_none() {
+            cache.order.push_back(uri.to_string());
+        }
+        while cache.order.len() > self.config.cache_size {
+            if let Some(oldest) = cache.order.pop_front() {
+                cache.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn remember_failure(&self, uri: &str, reason: &str) {
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+        if cache.failures.len() >= self.config.cache_size {
+            cache.failures.retain(|_, (_, until)| now < *until);
+        }
+        if cache.failures.len() < self.config.cache_size {
+            let until = now + Duration::from_secs(self.config.failure_ttl_secs);
+            cache.failures.insert(uri.to_string(), (reason.to_string(), until));
+        }
+    }
+}
+
+/// Gateway-independent location of content-addressed metadata.
+#[derive(Debug, PartialEq, Eq)]
+enum ContentPath {
+    /// `<cid>[/path]`
+    Ipfs(String),
+    /// `<transaction id>[/path]`
+    Arweave(String),
+}
+
+/// Recognizes `ipfs://`, `ar://`, path gateways (`https://<host>/ipfs/<cid>`),
+/// subdomain gateways (`https://<cid>.ipfs.<host>/`) and `arweave.net` URLs.
+fn content_path(uri: &str) -> Option<ContentPath> {
+    if let Some(path) = uri.strip_prefix("ipfs://") {
+        let path = path.strip_prefix("ipfs/").unwrap_or(path);
+        return Some(ContentPath::Ipfs(path.to_string()));
+    }
+    if let Some(path) = uri.strip_prefix("ar://") {
+        return Some(ContentPath::Arweave(path.to_string()));
+    }
+
+    let url = Url::parse(uri).ok()?;
+    let host = url.host_str()?;
+    let path = url.path().trim_start_matches('/');
+    if let Some(cid_path) = path.strip_prefix("ipfs/") {
+        return Some(ContentPath::Ipfs(cid_path.to_string()));
+    }
+    if let Some((cid, _)) = host.split_once(".ipfs.") {
+        return Some(ContentPath::Ipfs(join(cid, path)));
+    }
+    if host == "arweave.net" || host.ends_with(".arweave.net") {
+        return Some(ContentPath::Arweave(path.to_string()));
+    }
+    None
+}
+
+fn join(base: &str, path: &str) -> String {
+    if path.is_empty() {
+        return base.trim_end_matches('/').to_string();
+    }
+    format!("{}/{}", base.trim_end_matches('/'), path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CID: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+
+    fn resolver() -> MetadataResolver {
+        MetadataResolver::new(MetadataConfig {
+            ipfs_gateways: vec!["https://ipfs.io/ipfs".to_string(), "https://dweb.link/ipfs/".to_string()],
+            arweave_gateways: vec!["https://arweave.net".to_string()],
+            ..MetadataConfig::default()
+        }).unwrap()
+    }
+
+    #[test]
+    fn content_path_recognizes_ipfs_forms() {
+        let expected = Some(ContentPath::Ipfs(format!("{}/meta.json", CID)));
+        assert_eq!(content_path(&format!("ipfs://{}/meta.json", CID)), expected);
+        assert_eq!(content_path(&format!("ipfs://ipfs/{}/meta.json", CID)), expected);
+        assert_eq!(content_path(&format!("https://gateway.pinata.cloud/ipfs/{}/meta.json", CID)), expected);
+    }
+
+    /// Subdomain gateways carry lowercase CIDv1s, since hostnames are case-insensitive.
+    #[test]
+    fn content_path_recognizes_subdomain_gateways() {
+        let cid = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+        let expected = Some(ContentPath::Ipfs(format!("{}/meta.json", cid)));
+        assert_eq!(content_path(&format!("https://{}.ipfs.w3s.link/meta.json", cid)), expected);
+        assert_eq!(content_path(&format!("https://{}.ipfs.dweb.link/", cid)), Some(ContentPath::Ipfs(cid.to_string())));
+    }
+
+    #[test]
+    fn content_path_recognizes_arweave_forms() {
+        let expected = Some(ContentPath::Arweave("abc123".to_string()));
+        assert_eq!(content_path("ar://abc123"), expected);
+        assert_eq!(content_path("https://arweave.net/abc123"), expected);
+        assert_eq!(content_path("https://www.arweave.net/abc123"), expected);
+    }
+
+    #[test]
+    fn content_path_ignores_other_urls() {
+        assert_eq!(content_path("https://example.com/meta.json"), None);
+        assert_eq!(content_path("not a uri"), None);
+        assert_eq!(content_path(""), None);
+    }
+
+    #[test]
+    fn candidates_start_with_the_original_url() {
+        let uri = format!("https://gateway.pinata.cloud/ipfs/{}", CID);
+        assert_eq!(resolver().candidates(&uri), vec![
+            uri.clone(),
+            format!("https://ipfs.io/ipfs/{}", CID),
+            format!("https://dweb.link/ipfs/{}", CID),
+        ]);
+    }
+
+    #[test]
+    fn candidates_skip_duplicates_and_unsupported_schemes() {
+        let resolver = resolver();
+        let uri = format!("https://ipfs.io/ipfs/{}", CID);
+        assert_eq!(resolver.candidates(&uri), vec![uri.clone(), format!("https://dweb.link/ipfs/{}", CID)]);
+        assert_eq!(resolver.candidates("ar://abc123"), vec!["https://arweave.net/abc123".to_string()]);
+        assert_eq!(resolver.candidates("https://example.com/meta.json"), vec!["https://example.com/meta.json"]);
+        assert!(resolver.candidates("ftp://example.com/meta.json").is_empty());
+    }
+
+    #[test]
+    fn join_handles_slashes() {
+        assert_eq!(join("https://ipfs.io/ipfs", "cid/a.json"), "https://ipfs.io/ipfs/cid/a.json");
+        assert_eq!(join("https://ipfs.io/ipfs/", "cid"), "https://ipfs.io/ipfs/cid");
+        assert_eq!(join("https://arweave.net/", ""), "https://arweave.net");
+    }
+
+    #[tokio::test]
+    async fn failed_uris_are_not_refetched() {
+        let resolver = resolver();
+        let uri = "ftp://example.com/meta.json";
+        let metadata = resolver.resolve(uri, "Token", "TKN").await;
+        assert!(metadata.unavailable.is_some());
+        assert!(resolver.cache.lock().unwrap().failures.contains_key(uri));
+
+        let again = resolver.resolve(uri, "Token", "TKN").await;
+        assert_eq!(again.unavailable, metadata.unavailable);
+        assert_eq!(again.symbol, "TKN");
+    }
+}
@@ -1,2 +1,3 @@
 pub mod user_data;
-pub mod embed;
\ No newline at end of file
+pub mod embed;
+pub mod metadata;
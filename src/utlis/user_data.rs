@@ -1,207 +1,591 @@
-use std::{ error::Error, str::FromStr, time::Duration};
-use reqwest::Client;
+use std::{fmt, str::FromStr};
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use solana_account_decoder::UiAccountData;
-use solana_client::{nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig, rpc_request::TokenAccountsFilter, rpc_response::RpcKeyedAccount};
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
-use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInstruction, UiMessage, UiParsedInstruction, UiTransactionEncoding};
+use serde_json::{Map, Value};
+use url::Url;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
 use mpl_token_metadata::accounts::Metadata;
 
+use crate::idl::BorshReader;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ParsedData {
-    pub info: TokenInfo,
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+const METAPLEX_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+/// Most accounts `getMultipleAccounts` accepts per request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Token-2022 accounts are padded to the size of a token account before the
+/// account type byte and the extension TLV entries.
+const TOKEN_2022_ACCOUNT_TYPE_OFFSET: usize = 165;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const EXTENSION_UNINITIALIZED: u16 = 0;
+const EXTENSION_TOKEN_METADATA: u16 = 19;
+
+/// Program that owns a mint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenProgram {
+    Spl,
+    Token2022,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TokenInfo {
-    pub isNative: bool,
-    pub mint: String,
-    pub owner: String,
-    pub state: String,
-    pub tokenAmount: TokenAmount,
+impl TokenProgram {
+    pub fn from_owner(owner: &Pubkey) -> Option<Self> {
+        match owner.to_string().as_str() {
+            TOKEN_PROGRAM_ID => Some(TokenProgram::Spl),
+            TOKEN_2022_PROGRAM_ID => Some(TokenProgram::Token2022),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TokenAmount {
-    pub amount: String,
-    pub decimals: u8,
-    pub uiAmount: f64,
-    pub uiAmountString: String,
+impl fmt::Display for TokenProgram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenProgram::Spl => write!(f, "SPL Token"),
+            TokenProgram::Token2022 => write!(f, "Token-2022"),
+        }
+    }
 }
 
+/// Where a mint's on-chain name, symbol and uri were read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataSource {
+    /// Metaplex metadata PDA
+    Metaplex,
+    /// Token-2022 metadata extension on the mint itself
+    MintExtension,
+}
 
-pub async fn get_user_created_tokens(user: Pubkey, client: RpcClient)->Vec<Metadata> {
-    let program_id = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+/// A mint's on-chain metadata, for either token program.
+#[derive(Debug, Clone)]
+pub struct OnchainMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub update_authority: Option<Pubkey>,
+    pub token_program: TokenProgram,
+    pub source: MetadataSource,
+}
 
-    let token_accounts:Vec<RpcKeyedAccount> = client
-        .get_token_accounts_by_owner(&user, TokenAccountsFilter::ProgramId(program_id)).await
-        .unwrap();
-    let mut tokens = Vec::new();
-    for account in token_accounts {
-        let account_data = account.account.data;
-        match account_data {
-            UiAccountData::Json(parsed_account) => {
-                let parsed: Value = parsed_account.parsed.clone();
-                if let Ok(token) = serde_json::from_value::<ParsedData>(parsed) {
-                    let token_info = token.info;
-                    tokens.push(token_info.mint);
-                } else {
-                    println!("Failed to parse token data");
-                }
-           
-            }
-            UiAccountData::Binary(data, _) => {
-                println!("Binary data: {}", data);
+/// Metaplex metadata PDA of a mint.
+pub fn metadata_pda(mint: &Pubkey) -> Pubkey {
+    let program_id = Pubkey::from_str(METAPLEX_PROGRAM_ID).unwrap();
+    Pubkey::find_program_address(&[b"metadata", program_id.as_ref(), mint.as_ref()], &program_id).0
+}
+
+/// Reads a mint's metadata at `commitment`: from the Token-2022 metadata
+/// extension when the mint carries it, otherwise from its Metaplex metadata
+/// PDA. A mint seen in a `processed` notification may not exist yet at a
+/// higher commitment, so read at the one it was seen at.
+pub async fn get_token_metadata(
+    client: &RpcClient,
+    mint: &Pubkey,
+    commitment: CommitmentConfig
+) -> anyhow::Result<OnchainMetadata> {
+    get_token_metadata_batch(client, &[*mint], commitment)
+        .await
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| Err(anyhow::anyhow!("no metadata result for {}", mint)))
+}
+
+/// [`get_token_metadata`] for many mints with `getMultipleAccounts`: mints
+/// first, then the Metaplex PDAs of those without the Token-2022 extension,
+/// `MAX_MULTIPLE_ACCOUNTS` per request. Results are in the order of `mints`;
+/// a missing or undecodable account (or a failed request) only fails the
+/// mints it concerns.
+pub async fn get_token_metadata_batch(
+    client: &RpcClient,
+    mints: &[Pubkey],
+    commitment: CommitmentConfig
+) -> Vec<anyhow::Result<OnchainMetadata>> {
+    let mint_accounts = get_multiple_accounts(client, mints, commitment).await;
+
+    let mut results = Vec::with_capacity(mints.len());
+    let mut needs_pda = Vec::new();
+    for (index, (mint, account)) in mints.iter().zip(mint_accounts).enumerate() {
+        match account.and_then(|account| inspect_mint(mint, &account)) {
+            Ok(MintMetadata::Extension(metadata)) => results.push(Ok(metadata)),
+            Ok(MintMetadata::Metaplex(token_program)) => {
+                needs_pda.push((index, token_program));
+                results.push(Err(anyhow::anyhow!("metadata of {} not fetched", mint)));
             }
-            UiAccountData::LegacyBinary(_) => todo!(),
-            
+            Err(e) => results.push(Err(e)),
         }
     }
-    let mut user_tokens:Vec<Metadata>= Vec::new();
-    for token in tokens {
-        let metadata=get_token_metadata(&client, &Pubkey::from_str(&token).unwrap()).await.unwrap();
-        let program=find_mint_token_program(&client, &Pubkey::from_str(&token).unwrap()).await.unwrap();
-        if !program.is_empty() {
-            user_tokens.push(metadata);
-        }
+
+    let pdas: Vec<Pubkey> = needs_pda.iter().map(|(index, _)| metadata_pda(&mints[*index])).collect();
+    let pda_accounts = get_multiple_accounts(client, &pdas, commitment).await;
+    for ((index, token_program), account) in needs_pda.into_iter().zip(pda_accounts) {
+        results[index] = account
+            .and_then(|account| decode_metaplex_metadata(&account.data))
+            .map(|metadata| OnchainMetadata { token_program, ..metadata })
+            .with_context(|| format!("no metadata for {}", mints[index]));
     }
-    user_tokens
+    results
+}
 
+enum MintMetadata {
+    /// Read from the mint's Token-2022 metadata extension
+    Extension(OnchainMetadata),
+    /// To be read from the Metaplex PDA
+    Metaplex(TokenProgram),
 }
 
+fn inspect_mint(mint: &Pubkey, account: &Account) -> anyhow::Result<MintMetadata> {
+    let token_program = TokenProgram::from_owner(&account.owner)
+        .with_context(|| format!("{} is not a mint (owner {})", mint, account.owner))?;
+    if token_program == TokenProgram::Token2022 {
+        if let Some(metadata) = mint_extension_metadata(&account.data)? {
+            return Ok(MintMetadata::Extension(metadata));
+        }
+    }
+    Ok(MintMetadata::Metaplex(token_program))
+}
 
-pub async fn find_mint_token_program(
+/// `getMultipleAccounts` in chunks of `MAX_MULTIPLE_ACCOUNTS`, one result per
+/// address.
+async fn get_multiple_accounts(
     client: &RpcClient,
-    mint_address: &Pubkey
-) -> Result<String, Box<dyn Error>> {
-    let mut total_signatures: Vec<solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature> =
-        Vec::new();
-
-        let mut before: Option<Signature> = None;
-    let mut max = 1000;
-
-    
-    loop {
-        let signatures_result = client.get_signatures_for_address_with_config(
-            mint_address,
-            GetConfirmedSignaturesForAddress2Config {
-                limit: None,
-                commitment: None,
-                before,
-                until: None,
+    addresses: &[Pubkey],
+    commitment: CommitmentConfig
+) -> Vec<anyhow::Result<Account>> {
+    let mut accounts = Vec::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        match client.get_multiple_accounts_with_commitment(chunk, commitment).await {
+            Ok(response) => {
+                // A short response fails the addresses it leaves out
+                let mut response = response.value.into_iter();
+                accounts.extend(
+                    chunk
+                        .iter()
+                        .map(|address| {
+                            response.next().flatten().with_context(|| format!("account {} not found", address))
+                        })
+                );
             }
-        ).await;
+            Err(e) => {
+                let e = e.to_string();
+                accounts.extend(chunk.iter().map(|_| Err(anyhow::anyhow!("getMultipleAccounts failed: {}", e))));
+            }
+        }
+    }
+    accounts
+}
+
+pub fn decode_metaplex_metadata(data: &[u8]) -> anyhow::Result<OnchainMetadata> {
+    let metadata = Metadata::safe_deserialize(data)
+        .map_err(|e| anyhow::anyhow!("failed to deserialize Metaplex metadata: {:?}", e))?;
+    // Metaplex pads the strings with NULs to fixed lengths
+    let trim = |value: &str| value.trim_end_matches('\0').to_string();
+    Ok(OnchainMetadata {
+        name: trim(&metadata.name),
+        symbol: trim(&metadata.symbol),
+        uri: trim(&metadata.uri),
+        update_authority: Some(metadata.update_authority),
+        token_program: TokenProgram::Spl,
+        source: MetadataSource::Metaplex,
+    })
+}
+
+/// Decodes the Token-2022 `TokenMetadata` extension of a mint account.
+/// `None` if the mint has no such extension, e.g. when its metadata pointer
+/// points to a Metaplex PDA instead.
+pub fn mint_extension_metadata(data: &[u8]) -> anyhow::Result<Option<OnchainMetadata>> {
+    if data.len() <= TOKEN_2022_ACCOUNT_TYPE_OFFSET || data[TOKEN_2022_ACCOUNT_TYPE_OFFSET] != ACCOUNT_TYPE_MINT {
+        return Ok(None);
+    }
 
-        if !signatures_result.is_ok() {
-            println!("signatures_result {:?}", signatures_result);
+    let mut extensions = BorshReader::new(&data[TOKEN_2022_ACCOUNT_TYPE_OFFSET + 1..]);
+    while let Ok(header) = extensions.take::<4>() {
+        let extension_type = u16::from_le_bytes([header[0], header[1]]);
+        let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        if extension_type == EXTENSION_UNINITIALIZED {
             break;
         }
-        let _signatures_result = signatures_result.unwrap().clone();
+        let value = extensions.take_slice(len)?;
+        if extension_type != EXTENSION_TOKEN_METADATA {
+            continue;
+        }
 
-        before = Some(Signature::from_str(&_signatures_result.last().unwrap().signature).unwrap());
-        total_signatures.extend(_signatures_result.clone());
-        if total_signatures.len() == max {
-            max += 1000;
-        } else {
-            break;
+        let mut reader = BorshReader::new(value);
+        // An all-zero update authority means none
+        let update_authority = Some(reader.pubkey()?).filter(|authority| *authority != Pubkey::default());
+        let _mint = reader.pubkey()?;
+        return Ok(
+            Some(OnchainMetadata {
+                name: reader.string()?,
+                symbol: reader.string()?,
+                uri: reader.string()?,
+                update_authority,
+                token_program: TokenProgram::Token2022,
+                source: MetadataSource::MintExtension,
+            })
+        );
+    }
+    Ok(None)
+}
+
+/// Off-chain metadata JSON a token's URI points to. Fields missing from the
+/// JSON, or of the wrong type, are left empty.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(from = "Map<String, Value>")]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub description: String,
+    pub image: String,
+    pub website: Option<String>,
+    pub twitter: Option<String>,
+    pub telegram: Option<String>,
+    /// Links other than the website, twitter and telegram ones
+    pub links: Vec<TokenLink>,
+    /// Why the JSON couldn't be fetched, if it couldn't; the other fields
+    /// then only hold what the launch itself carries
+    #[serde(skip)]
+    pub unavailable: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TokenLink {
+    pub label: String,
+    pub url: String,
+}
+
+impl TokenMetadata {
+    /// Placeholder for a token whose metadata couldn't be fetched.
+    pub fn unavailable(name: &str, symbol: &str, reason: String) -> Self {
+        Self {
+            name: name.to_string(),
+            symbol: symbol.to_string(),
+            unavailable: Some(reason),
+            ..Self::default()
+        }
+    }
+
+    /// Differences between this metadata and the `name` and `symbol` the
+    /// token was created with, ignoring case and surrounding whitespace.
+    /// Empty if the metadata is unavailable or leaves them out.
+    pub fn mismatches(&self, name: &str, symbol: &str) -> Vec<String> {
+        if self.unavailable.is_some() {
+            return Vec::new();
         }
+        let differs = |offchain: &str, onchain: &str| {
+            !offchain.trim().is_empty() && !offchain.trim().eq_ignore_ascii_case(onchain.trim())
+        };
+        let mut mismatches = Vec::new();
+        if differs(&self.name, name) {
+            mismatches.push(format!("name `{}` (created as `{}`)", self.name.trim(), name.trim()));
+        }
+        if differs(&self.symbol, symbol) {
+            mismatches.push(format!("symbol `{}` (created as `{}`)", self.symbol.trim(), symbol.trim()));
+        }
+        mismatches
     }
 
-    let signature_info = &total_signatures.last().unwrap();
+    fn add_link(&mut self, label: &str, url: &str) {
+        let Some(url) = link_url(label, url) else {
+            return;
+        };
+        let slot = match link_kind(label, &url) {
+            Some(LinkKind::Website) => &mut self.website,
+            Some(LinkKind::Twitter) => &mut self.twitter,
+            Some(LinkKind::Telegram) => &mut self.telegram,
+            None => {
+                let link = TokenLink { label: label.trim().replace(['[', ']'], ""), url };
+                if !self.links.contains(&link) {
+                    self.links.push(link);
+                }
+                return;
+            }
+        };
+        if slot.is_none() {
+            *slot = Some(url);
+        }
+    }
+}
 
-    let signature = Signature::from_str(&signature_info.signature).unwrap();
+/// Metadata JSON comes in several shapes: links as top-level fields
+/// (`website`, `twitter`, ...), in a Metaplex-style `extensions` object, or
+/// as `links`, `websites` and `socials` lists of `{label|type, url}` objects.
+impl From<Map<String, Value>> for TokenMetadata {
+    fn from(json: Map<String, Value>) -> Self {
+        let text = |key: &str| json.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+        let mut metadata = TokenMetadata {
+            name: text("name"),
+            symbol: text("symbol"),
+            description: text("description"),
+            image: text("image"),
+            ..TokenMetadata::default()
+        };
 
-    let tx_option: EncodedConfirmedTransactionWithStatusMeta = client.get_transaction_with_config(
-        &signature,
-        RpcTransactionConfig {
-            encoding: Some(UiTransactionEncoding::JsonParsed),
-            commitment: Some(CommitmentConfig::finalized()),
-            max_supported_transaction_version: Some(0),
+        for label in ["website", "twitter", "telegram", "discord"] {
+            if let Some(url) = json.get(label).and_then(Value::as_str) {
+                metadata.add_link(label, url);
+            }
         }
-    ).await?;
-
-    let transaction: &solana_transaction_status::EncodedTransaction = &tx_option.transaction.transaction;
-    match transaction {
-        EncodedTransaction::Json(parsed_account) => {match &parsed_account.message {
-            UiMessage::Parsed(message) => {
-                for instruction in &message.instructions {
-                    
-                    match instruction {
-                        UiInstruction::Parsed(instruction)=>{
-                            match  instruction{
-                                UiParsedInstruction::PartiallyDecoded(instruction) => {
-                                    if instruction.program_id.to_string() =="MoonCVVNZFSYkqNXP6bxHLPL6QQJiMagDL3qcqUQTrG" {
-                                        return Ok(instruction.program_id.to_string())
-                                    }else {
-                                        continue;
-                                    
-                                    }
-                                }
-                                UiParsedInstruction::Parsed(_) => {continue},
-                            }
+        for key in ["extensions", "links", "websites", "socials"] {
+            match json.get(key) {
+                Some(Value::Object(links)) => {
+                    for (label, url) in links {
+                        if let Some(url) = url.as_str() {
+                            metadata.add_link(label, url);
+                        }
+                    }
+                }
+                Some(Value::Array(links)) => {
+                    for link in links {
+                        let field = |name: &str| link.get(name).and_then(Value::as_str);
+                        if let Some(url) = field("url") {
+                            metadata.add_link(field("label").or(field("type")).or(field("name")).unwrap_or(key), url);
                         }
-                        UiInstruction::Compiled(_) => todo!(),
                     }
                 }
+                _ => {}
             }
-            UiMessage::Raw(_) => todo!(),
-            
-        }}
-        EncodedTransaction::LegacyBinary(_) => todo!(),
-        EncodedTransaction::Binary(_, _) => todo!(),
-        EncodedTransaction::Accounts(_) => todo!(), }
-    Ok("".to_owned())
+        }
+        metadata
+    }
+}
 
+enum LinkKind {
+    Website,
+    Twitter,
+    Telegram,
+}
 
+fn link_kind(label: &str, url: &str) -> Option<LinkKind> {
+    let label = label.trim().to_ascii_lowercase();
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.trim_start_matches("www.").to_string()))
+        .unwrap_or_default();
+    if label == "twitter" || label == "x" || host == "twitter.com" || host == "x.com" {
+        Some(LinkKind::Twitter)
+    } else if label == "telegram" || host == "t.me" || host == "telegram.me" {
+        Some(LinkKind::Telegram)
+    } else if label == "website" || label == "websites" || label == "web" {
+        Some(LinkKind::Website)
+    } else {
+        None
+    }
 }
 
+/// `url` as an http(s) URL, expanding bare twitter and telegram handles.
+/// Anything else is dropped, so links are safe to put in embeds.
+fn link_url(label: &str, url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return None;
+    }
+    if let Ok(parsed) = Url::parse(url) {
+        return matches!(parsed.scheme(), "http" | "https").then(|| url.to_string());
+    }
+    let handle = url.trim_start_matches('@');
+    if !handle.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    match label.trim().to_ascii_lowercase().as_str() {
+        "twitter" | "x" => Some(format!("https://x.com/{}", handle)),
+        "telegram" => Some(format!("https://t.me/{}", handle)),
+        _ => None,
+    }
+}
 
-pub async fn get_token_metadata(
-    client: &RpcClient,
-    mint_address: &Pubkey
-) -> Result<Metadata, Box<dyn Error>> {
-    let pump_program_id = Pubkey::from_str("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s").expect(
-        "Failed to parse pubkey"
-    );
-    let (metadata_key, _) = Pubkey::find_program_address(
-        &[b"metadata", &pump_program_id.as_ref(), &mint_address.as_ref()],
-        &pump_program_id
-    );
-
-    let account_data = match client.get_account_data(&metadata_key).await {
-        Ok(data) => data,
-        Err(e) => {
-            return Err(Box::new(e));
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const MINT_SIZE: usize = 82;
+
+    fn borsh_string(data: &mut Vec<u8>, value: &str) {
+        data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        data.extend_from_slice(value.as_bytes());
+    }
+
+    fn tlv(data: &mut Vec<u8>, extension_type: u16, value: &[u8]) {
+        data.extend_from_slice(&extension_type.to_le_bytes());
+        data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        data.extend_from_slice(value);
+    }
+
+    /// A Token-2022 mint account as `getAccountInfo` returns it: the base
+    /// mint, padding to the token account size, the account type, then a
+    /// `MetadataPointer` (18) pointing at the mint itself and the
+    /// `TokenMetadata` (19) extension.
+    fn token_2022_mint(mint: &Pubkey, update_authority: &Pubkey, metadata: Option<(&str, &str, &str)>) -> Vec<u8> {
+        let mut data = vec![0u8; MINT_SIZE];
+        // Supply 1e18, 9 decimals, initialized
+        data[36..44].copy_from_slice(&1_000_000_000_000_000_000u64.to_le_bytes());
+        data[44] = 9;
+        data[45] = 1;
+        data.resize(TOKEN_2022_ACCOUNT_TYPE_OFFSET, 0);
+        data.push(ACCOUNT_TYPE_MINT);
+
+        let mut pointer = update_authority.to_bytes().to_vec();
+        pointer.extend_from_slice(mint.as_ref());
+        tlv(&mut data, 18, &pointer);
+
+        if let Some((name, symbol, uri)) = metadata {
+            let mut value = update_authority.to_bytes().to_vec();
+            value.extend_from_slice(mint.as_ref());
+            borsh_string(&mut value, name);
+            borsh_string(&mut value, symbol);
+            borsh_string(&mut value, uri);
+            // One additional key/value pair
+            value.extend_from_slice(&1u32.to_le_bytes());
+            borsh_string(&mut value, "website");
+            borsh_string(&mut value, "https://example.com");
+            tlv(&mut data, EXTENSION_TOKEN_METADATA, &value);
         }
-    };
-    let metadata: Metadata = Metadata::safe_deserialize(&mut &account_data).map_err(|e|
-        format!("Failed to deserialize metadata: {:?}", e)
-    )?;
+        data
+    }
 
-    Ok(metadata)
-}
+    #[test]
+    fn reads_token_metadata_extension() {
+        let mint = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let uri = "https://cf-ipfs.com/ipfs/QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+        let data = token_2022_mint(&mint, &authority, Some(("Moon Token", "MOON", uri)));
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct TokenMetadata {
-    pub name: String,
-    pub symbol: String,
-    pub description: String,
-    pub image: String,
-}
+        let metadata = mint_extension_metadata(&data).unwrap().unwrap();
+        assert_eq!(metadata.name, "Moon Token");
+        assert_eq!(metadata.symbol, "MOON");
+        assert_eq!(metadata.uri, uri);
+        assert_eq!(metadata.update_authority, Some(authority));
+        assert_eq!(metadata.token_program, TokenProgram::Token2022);
+        assert_eq!(metadata.source, MetadataSource::MintExtension);
+    }
+
+    #[test]
+    fn zero_update_authority_means_none() {
+        let data = token_2022_mint(&Pubkey::new_unique(), &Pubkey::default(), Some(("A", "A", "")));
+        assert_eq!(mint_extension_metadata(&data).unwrap().unwrap().update_authority, None);
+    }
+
+    #[test]
+    fn mint_without_metadata_extension() {
+        let data = token_2022_mint(&Pubkey::new_unique(), &Pubkey::new_unique(), None);
+        assert!(mint_extension_metadata(&data).unwrap().is_none());
+        // Plain SPL mint
+        assert!(mint_extension_metadata(&[0u8; MINT_SIZE]).unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated_extension_is_an_error() {
+        let mut data = token_2022_mint(&Pubkey::new_unique(), &Pubkey::new_unique(), Some(("Name", "SYM", "uri")));
+        data.truncate(data.len() - 30);
+        assert!(mint_extension_metadata(&data).is_err());
+    }
+
+    fn token_metadata(json: Value) -> TokenMetadata {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn reads_top_level_links() {
+        let metadata = token_metadata(json!({
+            "name": "Moon Token",
+            "symbol": "MOON",
+            "website": "https://moon.example",
+            "twitter": "@moontoken",
+            "telegram": "moon_chat",
+            "discord": "https://discord.gg/moon"
+        }));
+        assert_eq!(metadata.website.as_deref(), Some("https://moon.example"));
+        assert_eq!(metadata.twitter.as_deref(), Some("https://x.com/moontoken"));
+        assert_eq!(metadata.telegram.as_deref(), Some("https://t.me/moon_chat"));
+        assert_eq!(metadata.links, vec![TokenLink { label: "discord".to_string(), url: "https://discord.gg/moon".to_string() }]);
+    }
+
+    #[test]
+    fn reads_extensions_object() {
+        let metadata = token_metadata(json!({
+            "extensions": {
+                "website": "https://moon.example",
+                "x": "moontoken",
+                "[medium]": "https://medium.com/@moon"
+            }
+        }));
+        assert_eq!(metadata.website.as_deref(), Some("https://moon.example"));
+        assert_eq!(metadata.twitter.as_deref(), Some("https://x.com/moontoken"));
+        assert_eq!(metadata.links, vec![TokenLink { label: "medium".to_string(), url: "https://medium.com/@moon".to_string() }]);
+    }
+
+    #[test]
+    fn reads_link_lists() {
+        let metadata = token_metadata(json!({
+            "websites": [{ "label": "Website", "url": "https://moon.example" }],
+            "socials": [
+                { "type": "twitter", "url": "https://twitter.com/moontoken" },
+                { "type": "telegram", "url": "https://t.me/moon_chat" },
+                { "name": "github", "url": "https://github.com/moon" },
+                { "url": "https://moon.example/docs" }
+            ]
+        }));
+        assert_eq!(metadata.website.as_deref(), Some("https://moon.example"));
+        assert_eq!(metadata.twitter.as_deref(), Some("https://twitter.com/moontoken"));
+        assert_eq!(metadata.telegram.as_deref(), Some("https://t.me/moon_chat"));
+        assert_eq!(metadata.links, vec![
+            TokenLink { label: "github".to_string(), url: "https://github.com/moon".to_string() },
+            TokenLink { label: "socials".to_string(), url: "https://moon.example/docs".to_string() },
+        ]);
+    }
+
+    /// Links are classified by host as well as label, and the first one of
+    /// each kind wins; repeated extra links are kept once.
+    #[test]
+    fn classifies_links_by_host_and_keeps_the_first() {
+        let metadata = token_metadata(json!({
+            "twitter": "https://x.com/first",
+            "links": [
+                { "label": "community", "url": "https://x.com/second" },
+                { "label": "chat", "url": "https://telegram.me/moon" },
+                { "label": "docs", "url": "https://moon.example/docs" },
+                { "label": "docs", "url": "https://moon.example/docs" }
+            ]
+        }));
+        assert_eq!(metadata.twitter.as_deref(), Some("https://x.com/first"));
+        assert_eq!(metadata.telegram.as_deref(), Some("https://telegram.me/moon"));
+        assert_eq!(metadata.links.len(), 1);
+    }
 
-pub async fn get_pump_token_metadata(uri: &str) -> anyhow::Result<TokenMetadata> {
- println!("uri {:?}", uri);
- 
-    let client = Client::builder()
-        .timeout(Duration::from_secs(20)) // Set the timeout duration here
-        .build()?;
+    #[test]
+    fn drops_unsafe_and_unusable_links() {
+        let metadata = token_metadata(json!({
+            "website": "javascript:alert(1)",
+            "twitter": "not a handle",
+            "extensions": {
+                "discord": "moonserver",
+                "ftp": "ftp://moon.example/file",
+                "blog": "  "
+            }
+        }));
+        assert_eq!(metadata.website, None);
+        assert_eq!(metadata.twitter, None);
+        assert!(metadata.links.is_empty());
+    }
 
-    let resp = client.get(uri).send().await?;
-    let resp_str = resp.text().await?;
+    #[test]
+    fn link_urls() {
+        assert_eq!(link_url("twitter", " @moon_token ").as_deref(), Some("https://x.com/moon_token"));
+        assert_eq!(link_url("Telegram", "moonchat").as_deref(), Some("https://t.me/moonchat"));
+        assert_eq!(link_url("website", "moon.example"), None);
+        assert_eq!(link_url("website", "http://moon.example").as_deref(), Some("http://moon.example"));
+        assert_eq!(link_url("website", "data:text/html,hi"), None);
+    }
 
-    let body: TokenMetadata = serde_json::from_str(&resp_str)?;
-   
-    Ok(body)
-}
\ No newline at end of file
+    #[test]
+    fn name_and_symbol_mismatches() {
+        let metadata = token_metadata(json!({ "name": " moon token ", "symbol": "MOON2" }));
+        assert_eq!(metadata.mismatches("Moon Token", "MOON"), vec!["symbol `MOON2` (created as `MOON`)".to_string()]);
+
+        let blank = token_metadata(json!({ "name": "", "symbol": "  " }));
+        assert!(blank.mismatches("Moon Token", "MOON").is_empty());
+        let unavailable = TokenMetadata::unavailable("Other", "OTH", "timed out".to_string());
+        assert!(unavailable.mismatches("Moon Token", "MOON").is_empty());
+    }
+}
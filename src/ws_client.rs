@@ -1,7 +1,10 @@
 use {
     anyhow::Context,
     clap::{Parser, Subcommand, ValueEnum},
-    futures::{sink::SinkExt, stream::StreamExt},
+    futures::{
+        sink::SinkExt,
+        stream::{self, Stream, StreamExt},
+    },
     indicatif::{ProgressBar, ProgressStyle},
     jsonrpc_core::types::response::Output as RpcOutput,
     serde::Deserialize,
@@ -19,7 +22,11 @@ use {
     solana_rpc_client_api::response::RpcVersionInfo,
     solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature},
     solana_transaction_status::{TransactionDetails, UiTransactionEncoding},
-    std::{fmt, str::FromStr},
+    std::{
+        fmt,
+        str::FromStr,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
     tokio::net::TcpStream,
     tokio_tungstenite::{
         connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
@@ -373,6 +380,194 @@ pub async fn subscribe_experimental(
     }
 }
 
+/// Backoff and liveness settings for [`subscribe_resubscribing`].
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    /// Delay before the first reconnect attempt
+    pub initial_backoff: Duration,
+    /// Upper bound for the exponential backoff
+    pub max_backoff: Duration,
+    /// Reconnect if nothing arrives on the socket for this long
+    pub idle_timeout: Duration,
+    /// A socket that stays up this long, or delivers a notification, counts
+    /// as healthy and resets the backoff
+    pub min_uptime: Duration,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(60),
+            min_uptime: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectConfig {
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .initial_backoff
+            .saturating_mul(1u32 << attempt.min(16))
+            .min(self.max_backoff);
+        // Equal jitter: pick a point in [exp / 2, exp] so that reconnecting
+        // clients don't hammer the node in lockstep.
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let half = exp / 2;
+        half + half.mul_f64(f64::from(nanos % 1000) / 1000.0)
+    }
+}
+
+/// Item yielded by [`subscribe_resubscribing`].
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// The initial subscription was accepted with this id
+    Subscribed(usize),
+    /// The socket dropped; the reason is informational only
+    Disconnected(String),
+    /// A new socket was opened and the request re-sent, with the new id
+    Resubscribed(usize),
+    /// A notification belonging to the current subscription
+    Notification(Value),
+}
+
+struct Supervisor {
+    endpoint: String,
+    method: String,
+    params: Value,
+    config: ReconnectConfig,
+    socket: Option<WebSocketStream<MaybeTlsStream<TcpStream>>>,
+    subscription: Option<usize>,
+    /// Connections since the last healthy one
+    attempt: u32,
+    connected_at: Option<Instant>,
+}
+
+/// Whether `value` is a notification for another subscription than
+/// `current`, e.g. one left over from a previous socket.
+fn is_stale(value: &Value, current: Option<usize>) -> bool {
+    let subscription = value["params"]["subscription"].as_u64();
+    subscription.is_some() && subscription != current.map(|id| id as u64)
+}
+
+impl Supervisor {
+    async fn next_event(&mut self) -> StreamEvent {
+        loop {
+            let Some(socket) = self.socket.as_mut() else {
+                if let Some(event) = self.connect().await {
+                    return event;
+                }
+                continue;
+            };
+
+            let reason = match tokio::time::timeout(self.config.idle_timeout, socket.next()).await
+            {
+                Ok(Some(Ok(Message::Text(data)))) => {
+                    let value: Value = match serde_json::from_str(&data) {
+                        Ok(value) => value,
+                        Err(error) => {
+                            eprintln!("Dropping malformed message: {}", error);
+                            continue;
+                        }
+                    };
+                    if is_stale(&value, self.subscription) {
+                        continue;
+                    }
+                    self.attempt = 0;
+                    return StreamEvent::Notification(value);
+                }
+                Ok(Some(Ok(Message::Ping(payload)))) => {
+                    if let Err(error) = socket.send(Message::Pong(payload)).await {
+                        error.to_string()
+                    } else {
+                        continue;
+                    }
+                }
+                Ok(Some(Ok(Message::Close(frame)))) => format!("closed by server: {:?}", frame),
+                Ok(Some(Ok(_))) => continue,
+                Ok(Some(Err(error))) => error.to_string(),
+                Ok(None) => "stream ended".to_string(),
+                Err(_) => format!("no messages for {:?}", self.config.idle_timeout),
+            };
+
+            if let Some(mut socket) = self.socket.take() {
+                let _ = socket.close(None).await;
+            }
+            if self.connected_at.is_some_and(|at| at.elapsed() >= self.config.min_uptime) {
+                self.attempt = 0;
+            }
+            return StreamEvent::Disconnected(reason);
+        }
+    }
+
+    async fn connect(&mut self) -> Option<StreamEvent> {
+        if self.subscription.is_some() || self.attempt > 0 {
+            tokio::time::sleep(self.config.backoff(self.attempt)).await;
+        }
+
+        // Only reset once the new socket proves healthy, so one that
+        // subscribes and then drops straight away backs off like a failure
+        self.attempt = self.attempt.saturating_add(1);
+        match subscribe_experimental(&self.endpoint, &self.method, self.params.clone()).await {
+            Ok((SubscribeResponse::Subscribe(id), socket)) => {
+                let resubscribed = self.subscription.is_some();
+                self.socket = Some(socket);
+                self.subscription = Some(id);
+                self.connected_at = Some(Instant::now());
+                Some(if resubscribed {
+                    StreamEvent::Resubscribed(id)
+                } else {
+                    StreamEvent::Subscribed(id)
+                })
+            }
+            Ok((response, _)) => {
+                eprintln!("Unexpected subscribe response: {:?}", response);
+                None
+            }
+            Err(error) => {
+                eprintln!(
+                    "Subscribe attempt {} to {} failed: {:#}",
+                    self.attempt,
+                    self.endpoint,
+                    error
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Like [`subscribe_experimental`], but never ends: when the socket drops or
+/// goes idle it reconnects with jittered exponential backoff, re-sends the
+/// original request and keeps yielding notifications for the new
+/// subscription id. Lifecycle changes are reported inline as
+/// [`StreamEvent::Disconnected`] / [`StreamEvent::Resubscribed`].
+pub fn subscribe_resubscribing(
+    endpoint: &str,
+    method: &str,
+    params: Value,
+    config: ReconnectConfig,
+) -> impl Stream<Item = StreamEvent> {
+    let supervisor = Supervisor {
+        endpoint: endpoint.to_string(),
+        method: method.to_string(),
+        params,
+        config,
+        socket: None,
+        subscription: None,
+        attempt: 0,
+        connected_at: None,
+    };
+    stream::unfold(supervisor, |mut supervisor| async move {
+        let event = supervisor.next_event().await;
+        Some((event, supervisor))
+    })
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // whirligig_client::tracer_init()?;
@@ -657,4 +852,34 @@ async fn main() -> anyhow::Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_stays_within_jitter_bounds() {
+        let config = ReconnectConfig::default();
+        for attempt in 0..40 {
+            let exp = config
+                .initial_backoff
+                .saturating_mul(1u32 << attempt.min(16))
+                .min(config.max_backoff);
+            let backoff = config.backoff(attempt);
+            assert!(backoff >= exp / 2 && backoff <= exp, "attempt {}: {:?}", attempt, backoff);
+        }
+        assert!(config.backoff(0) <= config.initial_backoff);
+        assert!(config.backoff(40) >= config.max_backoff / 2);
+    }
+
+    #[test]
+    fn filters_notifications_from_stale_subscriptions() {
+        let notification = |id: u64| json!({ "method": "transactionNotification", "params": { "subscription": id, "result": {} } });
+        assert!(!is_stale(&notification(7), Some(7)));
+        assert!(is_stale(&notification(3), Some(7)));
+        assert!(is_stale(&notification(3), None));
+        // Anything that isn't a subscription notification is passed on
+        assert!(!is_stale(&json!({ "jsonrpc": "2.0", "id": 1, "result": 7 }), Some(7)));
+    }
+}